@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE node_execution;
+DROP TABLE run;
\ No newline at end of file
@@ -0,0 +1,40 @@
+-- Your SQL goes here
+CREATE TABLE run (
+    -- The run id reported in the `on_run_*` events
+    id TEXT NOT NULL PRIMARY KEY,
+    project_id INTEGER NOT NULL,
+    graph TEXT NOT NULL,
+    -- running, succeeded, failed or cancelled
+    status TEXT NOT NULL,
+    -- JSON encoded values
+    input TEXT,
+    output TEXT,
+    error TEXT,
+    started_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished_at DATETIME,
+
+    FOREIGN KEY (project_id) REFERENCES project (id) ON DELETE CASCADE
+);
+
+CREATE INDEX run_project_started ON run (project_id, started_at);
+
+CREATE TABLE node_execution (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    run_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    -- JSON encoded function graph calls leading to the node
+    call_stack TEXT NOT NULL,
+    -- running, succeeded, failed, cancelled or timedOut
+    status TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 1,
+    -- JSON encoded values
+    input TEXT,
+    output TEXT,
+    error TEXT,
+    started_at DATETIME NOT NULL,
+    finished_at DATETIME,
+
+    FOREIGN KEY (run_id) REFERENCES run (id) ON DELETE CASCADE
+);
+
+CREATE INDEX node_execution_run ON node_execution (run_id);
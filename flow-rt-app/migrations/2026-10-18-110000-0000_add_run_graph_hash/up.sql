@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- Fingerprint of the compiled graph, to tell whether it changed since the run
+ALTER TABLE run ADD COLUMN graph_hash TEXT;
\ No newline at end of file
@@ -10,6 +10,12 @@ use anyhow::Ok as anyhowOk;
 use dyn_rt::attach::AttachedPlugin;
 use dyn_rt::registry::{PluginRegistry, PluginRegistryBuilder};
 use dyn_rt::FnDescriptor;
+use flow_rt_shared::value::Value;
+use flow_rt_vm::compiler::FunctionCatalog;
+use flow_rt_vm::interpreter::{BoxFuture, FunctionHost};
+use flow_rt_vm::ir::FunctionRef;
+use flow_rt_vm::memo::marked_pure;
+use flow_rt_vm::typecheck::FunctionSignature;
 use serde::Deserialize;
 use serde::Serialize;
 use tauri::path::BaseDirectory;
@@ -150,3 +156,82 @@ fn impl_request_plugin_reload(
 
     Ok(())
 }
+
+/// Exposes the global plugin registry to the graph compiler and interpreter.
+pub(crate) struct RegistryHost;
+
+impl RegistryHost {
+    fn find_plugin(name: &str) -> Result<Arc<AttachedPlugin>, String> {
+        let registry_guard = APP_PLUGIN_REGISTRY
+            .get()
+            .ok_or_else(|| "Plugin registry is uninitialized.".to_string())?
+            .lock()
+            .map_err(|e| format!("Could not lock registry context: {e}"))?;
+
+        registry_guard
+            .get_plugins_map()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("Plugin {name} is not loaded."))
+    }
+
+    fn descriptor(plugin: &str, function: &str) -> Option<serde_json::Value> {
+        let plugin = Self::find_plugin(plugin).ok()?;
+        let descriptor = plugin
+            .functions
+            .iter()
+            .find(|(name, _)| *name == function)
+            .map(|(_, descriptor)| descriptor)?;
+
+        // Read the descriptor the same way the editor receives it through `fetch_plugins`.
+        serde_json::to_value(descriptor).ok()
+    }
+}
+
+impl FunctionCatalog for RegistryHost {
+    fn contains(&self, plugin: &str, function: &str) -> bool {
+        Self::find_plugin(plugin)
+            .map(|p| p.functions.iter().any(|(name, _)| name == function))
+            .unwrap_or(false)
+    }
+
+    fn signature(&self, plugin: &str, function: &str) -> Option<FunctionSignature> {
+        let descriptor = Self::descriptor(plugin, function)?;
+        let arguments = descriptor.get("schema")?;
+
+        Some(FunctionSignature::from_json_schema(
+            arguments,
+            descriptor.get("returns"),
+        ))
+    }
+
+    fn is_pure(&self, plugin: &str, function: &str) -> bool {
+        Self::descriptor(plugin, function).is_some_and(|descriptor| marked_pure(&descriptor))
+    }
+}
+
+impl FunctionHost for RegistryHost {
+    fn call<'a>(
+        &'a self,
+        function: &'a FunctionRef,
+        arguments: Value,
+    ) -> BoxFuture<'a, Result<Value, String>> {
+        Box::pin(async move {
+            // Clone the plugin out of the registry so the lock isn't held across the call.
+            let plugin = Self::find_plugin(&function.plugin)?;
+
+            // Plugins exchange plain JSON, which `Value` converts to and from losslessly.
+            plugin
+                .invoke(&function.function, serde_json::Value::from(arguments))
+                .await
+                .map(Value::from)
+                .map_err(|e| format!("{}::{} failed: {e}", function.plugin, function.function))
+        })
+    }
+
+    fn plugin_hash(&self, plugin: &str) -> Option<String> {
+        Self::find_plugin(plugin)
+            .ok()
+            .map(|plugin| plugin.blake3_hash.clone())
+    }
+}
@@ -1,20 +1,34 @@
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 use dyn_rt::registry::PluginRegistry;
+use flow_rt_vm::events::EventBus;
 use tauri::{async_runtime::block_on, Manager};
 use tauri_plugin_prevent_default::KeyboardShortcut;
 use tokio::sync::Mutex as TokioMutex;
 
 use crate::{
-    binding::init_plugin_repo, projects::ProjectConfiguration, settings::AppSettingsState,
+    binding::{init_plugin_repo, RegistryHost},
+    projects::ProjectConfiguration,
+    runtime::{
+        cancel::RunCancellations,
+        debug::DebugSessions,
+        environment::SelectedEnvironment,
+        live::PayloadStore,
+        memo::OutputCaches,
+        reload::{ProjectPrograms, ProjectWatcher},
+        EventRunReporter,
+    },
+    settings::AppSettingsState,
 };
 
 pub mod binding;
 pub mod fs;
 pub mod models;
 pub mod projects;
+pub mod runtime;
 pub mod schema;
 pub mod schemas;
+pub mod secrets;
 pub mod settings;
 pub mod state;
 
@@ -54,6 +68,13 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(prevent)
         .manage::<Mutex<Option<ProjectConfiguration>>>(Mutex::new(None))
+        .manage(PayloadStore::default())
+        .manage(DebugSessions::default())
+        .manage(RunCancellations::default())
+        .manage(OutputCaches::default())
+        .manage(ProjectPrograms::default())
+        .manage(ProjectWatcher::default())
+        .manage(SelectedEnvironment::default())
         .invoke_handler(tauri::generate_handler![
             binding::request_plugin_reload,
             binding::fetch_plugins,
@@ -71,6 +92,27 @@ pub fn run() {
             projects::get_current_project,
             projects::build_project_structure,
             projects::graphs::create_graph,
+            projects::graphs::save_graph,
+            runtime::run_graph,
+            runtime::check_graph,
+            runtime::fire_event,
+            runtime::live::get_run_payload,
+            runtime::cancel::cancel_run,
+            runtime::history::list_runs,
+            runtime::history::get_run,
+            runtime::history::delete_runs,
+            runtime::replay::replay_run,
+            runtime::debug::set_breakpoints,
+            runtime::debug::get_breakpoints,
+            runtime::debug::pause_run,
+            runtime::debug::step_run,
+            runtime::debug::continue_run,
+            runtime::debug::inspect_run,
+            runtime::environment::list_environments,
+            runtime::environment::select_environment,
+            secrets::add_secret,
+            secrets::list_secrets,
+            secrets::delete_secret,
         ])
         .setup(|app| {
             let settings_handle = app.handle();
@@ -86,6 +128,10 @@ pub fn run() {
 
             init_plugin_repo(app.handle());
 
+            let bus = EventBus::new(Arc::new(RegistryHost));
+            bus.set_observer(Arc::new(EventRunReporter::new(app.handle().clone())));
+            app.manage::<Arc<EventBus>>(bus);
+
             Ok(())
         })
         .run(tauri::generate_context!())
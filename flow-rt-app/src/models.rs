@@ -1,5 +1,6 @@
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
+use serde::Serialize;
 
 #[derive(Queryable, Selectable)]
 #[diesel(table_name = crate::schema::project)]
@@ -25,3 +26,54 @@ pub struct RecentProject {
     pub project_id: i32,
     pub opened_at: NaiveDateTime,
 }
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Clone, Debug)]
+#[diesel(table_name = crate::schema::run)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[serde(rename_all = "camelCase")]
+pub struct TrackedRun {
+    pub id: String,
+    pub project_id: i32,
+    pub graph: String,
+    pub status: String,
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub started_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+    pub graph_hash: Option<String>,
+}
+
+#[derive(Queryable, Selectable, Associations, Serialize, Clone, Debug)]
+#[diesel(belongs_to(TrackedRun, foreign_key = run_id))]
+#[diesel(table_name = crate::schema::node_execution)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[serde(rename_all = "camelCase")]
+pub struct NodeExecution {
+    pub id: i32,
+    pub run_id: String,
+    pub node_id: String,
+    pub call_stack: String,
+    pub status: String,
+    pub attempts: i32,
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub started_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Clone, Debug)]
+#[diesel(table_name = crate::schema::node_execution)]
+pub struct NewNodeExecution {
+    pub run_id: String,
+    pub node_id: String,
+    pub call_stack: String,
+    pub status: String,
+    pub attempts: i32,
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub started_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+}
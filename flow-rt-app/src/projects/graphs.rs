@@ -1,10 +1,15 @@
 use std::path::PathBuf;
 
+use flow_rt_vm::scheduler::schedule_flow_graph;
 use serde::{Deserialize, Serialize};
 use tauri::AppHandle;
 use tokio::io::AsyncWriteExt;
 
-use crate::projects::{has_project, ProjectConfiguration};
+use crate::{
+    projects::{has_project, ProjectConfiguration},
+    runtime::{graph_location, reload::reload_graph},
+    schemas::FlowGraph,
+};
 
 #[derive(Serialize, Deserialize)]
 pub(crate) struct NewGraphParameters {
@@ -13,7 +18,31 @@ pub(crate) struct NewGraphParameters {
     data: serde_json::Value,
 }
 
-async fn retrieve_project_configuration(app: AppHandle) -> Result<ProjectConfiguration, String> {
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct GraphStructureReport {
+    /// Nodes that aren't reachable from any entry node and can never run.
+    unreachable: Vec<String>,
+}
+
+/// Checks that `graph` can be scheduled, rejecting graphs that loop back on themselves.
+fn check_graph_structure(graph: &serde_json::Value) -> Result<GraphStructureReport, String> {
+    let graph = serde_json::from_value::<FlowGraph>(graph.clone())
+        .map_err(|e| format!("Invalid graph: {e}"))?;
+
+    let (dependencies, schedule) = schedule_flow_graph(&graph).map_err(|e| e.to_string())?;
+
+    Ok(GraphStructureReport {
+        unreachable: schedule
+            .unreachable
+            .iter()
+            .map(|index| dependencies.node_ids[*index].clone())
+            .collect(),
+    })
+}
+
+pub(crate) async fn retrieve_project_configuration(
+    app: AppHandle,
+) -> Result<ProjectConfiguration, String> {
     if !has_project(&app.clone()) {
         return Err(
             "No project currently loaded. Should not be called without managed project."
@@ -39,21 +68,22 @@ pub(crate) async fn create_graph(
 ) -> Result<serde_json::Value, String> {
     println!("{parameters:?}");
     let project_config = retrieve_project_configuration(app.clone()).await?;
-    let project_graphs_location = PathBuf::from(project_config.location).join("flows");
-    let graph_location = project_graphs_location.join(format!(
-        "{}.jfg",
-        parameters
-            .get("name")
-            .expect("name does not exist")
-            .as_str()
-            .unwrap()
-    ));
+    let project_graphs_location = PathBuf::from(&project_config.location).join("flows");
+    let name = parameters
+        .get("name")
+        .expect("name does not exist")
+        .as_str()
+        .unwrap()
+        .to_string();
+    let graph_location = project_graphs_location.join(format!("{name}.jfg"));
     println!("{graph_location:?}");
 
     if graph_location.exists() {
         return Err("Graph already exists.".into());
     }
 
+    check_graph_structure(&parameters)?;
+
     match tokio::fs::File::create_new(&graph_location).await {
         Ok(mut file) => {
             let data = serde_json::to_string_pretty(&parameters)
@@ -63,6 +93,8 @@ pub(crate) async fn create_graph(
                 .await
                 .map_err(|e| format!("Failed to write to file: {}", e))?;
 
+            reload_graph(&app, &project_config.location, &name);
+
             Ok(parameters)
         }
         Err(e) => Err(format!(
@@ -72,3 +104,29 @@ pub(crate) async fn create_graph(
     }
 }
 
+#[tauri::command]
+pub(crate) async fn save_graph(
+    app: AppHandle,
+    name: String,
+    graph: serde_json::Value,
+) -> Result<GraphStructureReport, String> {
+    let project_config = retrieve_project_configuration(app.clone()).await?;
+    let location = graph_location(&project_config.location, &name);
+
+    if !location.exists() {
+        return Err(format!("Graph {name} does not exist."));
+    }
+
+    let report = check_graph_structure(&graph)?;
+
+    let data =
+        serde_json::to_string_pretty(&graph).map_err(|e| format!("Serialization failed: {}", e))?;
+
+    tokio::fs::write(&location, data)
+        .await
+        .map_err(|e| format!("Failed to write to file: {}", e))?;
+
+    reload_graph(&app, &project_config.location, &name);
+
+    Ok(report)
+}
@@ -31,6 +31,8 @@ impl super::NewProject {
                     description: "".to_string(),
                     version: "0.1.0".to_string(),
                 },
+                scripts: None,
+                environments: None,
             })
             .unwrap_or_else(|_| panic!("Failed to create project toml exiting..."))
             .as_bytes(),
@@ -7,6 +7,7 @@ use std::{
 use diesel::{
     ExpressionMethods, OptionalExtension, QueryDsl, QueryResult, RunQueryDsl, SelectableHelper,
 };
+use flow_rt_vm::{environment::Environments, script::ScriptSandbox};
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter, Manager};
 
@@ -47,6 +48,12 @@ impl ProjectFile {
 pub(crate) struct ProjectTOMLConfiguration {
     #[serde(rename = "info", alias = "information", alias = "workspace")]
     pub info: ProjectInformation,
+    /// What the project's scripts may do. Falls back to the app settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scripts: Option<ScriptSandbox>,
+    /// The named environments `${env.NAME}` references resolve from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environments: Option<Environments>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -124,6 +131,17 @@ pub(crate) async fn open_project(
             let mut project_lock = state.lock().map_err(|_| "Failed to lock project state")?;
 
             *project_lock = Some(config.clone());
+            drop(project_lock);
+
+            crate::runtime::sandbox::configure_scripts(&app, &config.location).await;
+            crate::secrets::configure_secrets(&app, &config.location);
+            crate::runtime::environment::configure_environment(&app, &config.location);
+            crate::runtime::reload::load_project_graphs(&app, &config.location);
+            if let Err(e) = crate::runtime::reload::watch_project(&app, &config.location) {
+                eprintln!("{e}");
+            }
+            app.state::<crate::runtime::debug::DebugSessions>()
+                .load_breakpoints(&config.location);
 
             silence!(app.emit("on_current_project_changed", config.clone()));
 
@@ -186,7 +204,9 @@ fn get_project_configuration_from_location(
     })
 }
 
-fn toml_from_project_location(location: &PathBuf) -> Result<ProjectTOMLConfiguration, String> {
+pub(crate) fn toml_from_project_location(
+    location: &PathBuf,
+) -> Result<ProjectTOMLConfiguration, String> {
     let mut toml_str_buffer = String::new();
 
     let _config_wrapper_result = std::fs::File::open(location)
@@ -0,0 +1,41 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use flow_rt_vm::cancellation::Cancellation;
+use tauri::{AppHandle, Manager};
+
+/// The cancellations of the runs in progress, by run id.
+#[derive(Default)]
+pub(crate) struct RunCancellations {
+    runs: Mutex<HashMap<String, Arc<Cancellation>>>,
+}
+
+impl RunCancellations {
+    pub(crate) fn start(&self, run_id: &str) -> Arc<Cancellation> {
+        let cancellation = Arc::new(Cancellation::new());
+        self.lock().insert(run_id.to_string(), cancellation.clone());
+        cancellation
+    }
+
+    pub(crate) fn finish(&self, run_id: &str) {
+        self.lock().remove(run_id);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Arc<Cancellation>>> {
+        self.runs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Stops the run `run_id`. Nodes still waiting or running are reported with
+/// `on_node_cancelled`, and the run finishes with a `cancelled` error.
+#[tauri::command]
+pub(crate) async fn cancel_run(app: AppHandle, run_id: String) -> Result<(), String> {
+    app.state::<RunCancellations>()
+        .lock()
+        .get(&run_id)
+        .ok_or_else(|| format!("Run {run_id} is not in progress."))?
+        .cancel();
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use flow_rt_vm::debugger::{Debugger, Suspension};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    projects::graphs::retrieve_project_configuration,
+    runtime::live::{LivePayload, PayloadStore},
+};
+
+/// Where the breakpoints of the project at `project` are kept.
+pub(crate) fn project_breakpoints_file(project: &Path) -> PathBuf {
+    project.join(".flow").join("breakpoints.json")
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct BreakpointsFile {
+    breakpoints: BTreeSet<String>,
+}
+
+/// The breakpoints of the open project and the debuggers of the runs in progress.
+///
+/// Breakpoints apply to every run, including the ones already in progress.
+/// They are kept with the project and loaded again whenever it is opened.
+#[derive(Default)]
+pub(crate) struct DebugSessions {
+    breakpoints: Mutex<BTreeSet<String>>,
+    runs: Mutex<HashMap<String, Arc<Debugger>>>,
+}
+
+impl DebugSessions {
+    /// Creates the debugger of the run `run_id`, armed with the current breakpoints.
+    pub(crate) fn start(&self, run_id: &str) -> Arc<Debugger> {
+        let debugger = Arc::new(Debugger::new(lock(&self.breakpoints).iter().cloned()));
+        lock(&self.runs).insert(run_id.to_string(), debugger.clone());
+        debugger
+    }
+
+    pub(crate) fn finish(&self, run_id: &str) {
+        lock(&self.runs).remove(run_id);
+    }
+
+    /// Replaces the breakpoints with the ones kept for the project at
+    /// `project_location`. A project without any starts with none.
+    pub(crate) fn load_breakpoints(&self, project_location: &str) {
+        let file = project_breakpoints_file(Path::new(project_location));
+        let breakpoints = match fs::read(&file) {
+            Ok(contents) => serde_json::from_slice::<BreakpointsFile>(&contents)
+                .inspect_err(|e| eprintln!("Failed to read breakpoints at {file:?}: {e}"))
+                .unwrap_or_default(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BreakpointsFile::default(),
+            Err(e) => {
+                eprintln!("Failed to read breakpoints at {file:?}: {e}");
+                BreakpointsFile::default()
+            }
+        };
+        self.set_breakpoints(breakpoints.breakpoints);
+    }
+
+    fn set_breakpoints(&self, breakpoints: BTreeSet<String>) {
+        for debugger in lock(&self.runs).values() {
+            debugger.set_breakpoints(breakpoints.iter().cloned());
+        }
+        *lock(&self.breakpoints) = breakpoints;
+    }
+
+    fn debugger(&self, run_id: &str) -> Result<Arc<Debugger>, String> {
+        lock(&self.runs)
+            .get(run_id)
+            .cloned()
+            .ok_or_else(|| format!("Run {run_id} is not in progress."))
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// What a debugged run is doing, with large edge values sent by reference.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RunInspection {
+    suspended: Vec<Suspension>,
+    edges: BTreeMap<String, LivePayload>,
+    breakpoints: BTreeSet<String>,
+}
+
+/// Replaces the breakpoints of the open project with `node_ids`, keeping
+/// them for the next time it is opened.
+#[tauri::command]
+pub(crate) async fn set_breakpoints(
+    app: AppHandle,
+    node_ids: Vec<String>,
+) -> Result<Vec<String>, String> {
+    let project_config = retrieve_project_configuration(app.clone()).await?;
+    let file = project_breakpoints_file(Path::new(&project_config.location));
+    let breakpoints = BreakpointsFile {
+        breakpoints: node_ids.into_iter().collect(),
+    };
+
+    let contents = serde_json::to_vec_pretty(&breakpoints).map_err(|e| e.to_string())?;
+    file.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&file, contents))
+        .map_err(|e| format!("Failed to write breakpoints at {file:?}: {e}"))?;
+
+    app.state::<DebugSessions>()
+        .set_breakpoints(breakpoints.breakpoints.clone());
+    Ok(breakpoints.breakpoints.into_iter().collect())
+}
+
+#[tauri::command]
+pub(crate) async fn get_breakpoints(app: AppHandle) -> Vec<String> {
+    lock(&app.state::<DebugSessions>().breakpoints)
+        .iter()
+        .cloned()
+        .collect()
+}
+
+/// Suspends the run before the next node it starts.
+#[tauri::command]
+pub(crate) async fn pause_run(app: AppHandle, run_id: String) -> Result<(), String> {
+    app.state::<DebugSessions>().debugger(&run_id)?.pause();
+    Ok(())
+}
+
+/// Runs the first suspended node and stops at the next one. With `into`,
+/// stops inside the function graph the node calls instead of stepping over it.
+#[tauri::command]
+pub(crate) async fn step_run(
+    app: AppHandle,
+    run_id: String,
+    into: Option<bool>,
+) -> Result<(), String> {
+    let debugger = app.state::<DebugSessions>().debugger(&run_id)?;
+    match into.unwrap_or(false) {
+        true => debugger.step_into(),
+        false => debugger.step_over(),
+    }
+    Ok(())
+}
+
+/// Lets the run continue until the next breakpoint.
+#[tauri::command]
+pub(crate) async fn continue_run(app: AppHandle, run_id: String) -> Result<(), String> {
+    app.state::<DebugSessions>().debugger(&run_id)?.resume();
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) async fn inspect_run(app: AppHandle, run_id: String) -> Result<RunInspection, String> {
+    let snapshot = app.state::<DebugSessions>().debugger(&run_id)?.snapshot();
+    let payloads = app.state::<PayloadStore>();
+
+    Ok(RunInspection {
+        suspended: snapshot.suspended,
+        edges: snapshot
+            .edges
+            .into_iter()
+            .map(|(edge_id, value)| (edge_id, payloads.shrink(value)))
+            .collect(),
+        breakpoints: snapshot.breakpoints,
+    })
+}
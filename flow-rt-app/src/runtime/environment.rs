@@ -0,0 +1,98 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use flow_rt_vm::{environment::Environment, events::EventBus};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    projects::{graphs::retrieve_project_configuration, toml_from_project_location},
+    silence,
+};
+
+/// The environment selected for the open project in this session, when it
+/// isn't the `current` one of its `Flow.toml`.
+#[derive(Default)]
+pub(crate) struct SelectedEnvironment(Mutex<Option<String>>);
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EnvironmentList {
+    /// The environment runs use, if any.
+    current: Option<String>,
+    environments: Vec<String>,
+}
+
+/// Loads the environment `selected` of the project at `project_location`,
+/// or its current one.
+fn load_environment(project_location: &str, selected: Option<&str>) -> Result<Environment, String> {
+    let toml_location = PathBuf::from(project_location).join("Flow.toml");
+    toml_from_project_location(&toml_location)?
+        .environments
+        .unwrap_or_default()
+        .load(Path::new(project_location), selected)
+}
+
+/// Resolves the `${env.NAME}` references of graphs started from now on,
+/// including event graphs, from `environment`.
+fn apply_environment(app: &AppHandle, environment: Environment) {
+    silence!(app.emit("on_environment_changed", environment.name.clone()));
+    app.state::<Arc<EventBus>>()
+        .set_environment(Arc::new(environment));
+}
+
+/// Runs the graphs of the project at `project_location` in the current
+/// environment of its `Flow.toml`, dropping the selection of the previous
+/// project.
+pub(crate) fn configure_environment(app: &AppHandle, project_location: &str) {
+    *app.state::<SelectedEnvironment>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner()) = None;
+
+    // References fail with the variable they name rather than resolving
+    // from the environment of the previous project.
+    let environment = load_environment(project_location, None)
+        .inspect_err(|e| eprintln!("{e}"))
+        .unwrap_or_default();
+    apply_environment(app, environment);
+}
+
+#[tauri::command]
+pub(crate) async fn list_environments(app: AppHandle) -> Result<EnvironmentList, String> {
+    let project_config = retrieve_project_configuration(app.clone()).await?;
+    let toml_location = PathBuf::from(&project_config.location).join("Flow.toml");
+    let environments = toml_from_project_location(&toml_location)?
+        .environments
+        .unwrap_or_default();
+
+    let selected = app
+        .state::<SelectedEnvironment>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+
+    Ok(EnvironmentList {
+        current: selected.or(environments.current),
+        environments: environments.profiles.into_keys().collect(),
+    })
+}
+
+/// Runs the graphs of the open project in the environment `name` until
+/// another one is selected or the project is closed. `None` goes back to the
+/// current environment of its `Flow.toml`, re-reading its variables.
+#[tauri::command]
+pub(crate) async fn select_environment(app: AppHandle, name: Option<String>) -> Result<(), String> {
+    let project_config = retrieve_project_configuration(app.clone()).await?;
+    let environment = load_environment(&project_config.location, name.as_deref())?;
+
+    *app.state::<SelectedEnvironment>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner()) = name;
+    apply_environment(&app, environment);
+    Ok(())
+}
@@ -0,0 +1,359 @@
+use std::sync::Mutex;
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::{
+    BoolExpressionMethods, Connection, ExpressionMethods, OptionalExtension, QueryDsl, QueryResult,
+    RunQueryDsl, SelectableHelper,
+};
+use flow_rt_shared::value::Value;
+use flow_rt_vm::{
+    error::{RuntimeError, RuntimeErrorKind},
+    interpreter::{CallFrame, RunOutput},
+    ir::Program,
+    observer::{ExecutionEvent, RunObserver},
+};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{
+    models::{NewNodeExecution, NodeExecution, TrackedRun},
+    projects::graphs::retrieve_project_configuration,
+    schema::{node_execution, project, run},
+};
+
+/// Runs listed by [`list_runs`] when the filter doesn't set a limit.
+const DEFAULT_RUN_LIMIT: i64 = 100;
+
+/// Records a run of the open project in the state database.
+///
+/// The run itself is stored when it starts, so runs that never finish show
+/// up as `running`. Node executions are collected while the run is in
+/// progress and written together when it finishes.
+pub(crate) struct RunHistory {
+    run_id: String,
+    nodes: Mutex<Vec<NewNodeExecution>>,
+}
+
+impl RunHistory {
+    pub(crate) fn start(
+        project_location: &str,
+        run_id: &str,
+        graph: &str,
+        program: &Program,
+        input: &Value,
+    ) -> QueryResult<Self> {
+        let mut connection = crate::state::get_connection();
+
+        let project_id = project::table
+            .filter(project::directory_location.eq(project_location))
+            .select(project::id)
+            .first::<i32>(&mut *connection)?;
+
+        diesel::insert_into(run::table)
+            .values(&TrackedRun {
+                id: run_id.to_string(),
+                project_id,
+                graph: graph.to_string(),
+                status: "running".to_string(),
+                input: to_json(input),
+                output: None,
+                error: None,
+                started_at: now(),
+                finished_at: None,
+                graph_hash: Some(program.fingerprint()),
+            })
+            .execute(&mut *connection)?;
+
+        Ok(Self {
+            run_id: run_id.to_string(),
+            nodes: Mutex::default(),
+        })
+    }
+
+    /// Stores the outcome of the run along with every node it executed.
+    pub(crate) fn finish(&self, result: &Result<RunOutput, RuntimeError>) -> QueryResult<()> {
+        let status = match result {
+            Ok(_) => "succeeded",
+            Err(error) if error.kind == RuntimeErrorKind::Cancelled => "cancelled",
+            Err(_) => "failed",
+        };
+        let nodes = std::mem::take(&mut *self.lock());
+
+        crate::state::get_connection().transaction(|connection| {
+            diesel::update(run::table.find(&self.run_id))
+                .set((
+                    run::status.eq(status),
+                    run::output.eq(result.as_ref().ok().and_then(to_json)),
+                    run::error.eq(result.as_ref().err().and_then(to_json)),
+                    run::finished_at.eq(Some(now())),
+                ))
+                .execute(connection)?;
+
+            if !nodes.is_empty() {
+                diesel::insert_into(node_execution::table)
+                    .values(&nodes)
+                    .execute(connection)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<NewNodeExecution>> {
+        self.nodes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Updates the last execution of `node_id` at `call_stack` that hasn't finished yet.
+    fn update(
+        &self,
+        node_id: &str,
+        call_stack: &[CallFrame],
+        update: impl FnOnce(&mut NewNodeExecution),
+    ) {
+        let call_stack = to_json(call_stack).unwrap_or_default();
+        if let Some(execution) = self.lock().iter_mut().rev().find(|execution| {
+            execution.node_id == node_id
+                && execution.call_stack == call_stack
+                && execution.finished_at.is_none()
+        }) {
+            update(execution);
+        }
+    }
+
+    fn finish_node(
+        &self,
+        node_id: &str,
+        call_stack: &[CallFrame],
+        status: &str,
+        output: Option<&Value>,
+        error: Option<&RuntimeError>,
+    ) {
+        self.update(node_id, call_stack, |execution| {
+            execution.status = status.to_string();
+            execution.output = output.and_then(to_json);
+            execution.error = error.and_then(to_json);
+            execution.finished_at = Some(now());
+        });
+    }
+
+    /// Records `node_id` as finished with `status` without it ever starting.
+    fn push_unstarted(
+        &self,
+        node_id: String,
+        call_stack: &[CallFrame],
+        status: &str,
+        error: Option<&RuntimeError>,
+    ) {
+        let now = now();
+        self.lock().push(NewNodeExecution {
+            run_id: self.run_id.clone(),
+            node_id,
+            call_stack: to_json(call_stack).unwrap_or_default(),
+            status: status.to_string(),
+            attempts: 0,
+            input: None,
+            output: None,
+            error: error.and_then(to_json),
+            started_at: now,
+            finished_at: Some(now),
+        });
+    }
+}
+
+impl RunObserver for RunHistory {
+    fn notify(&self, event: ExecutionEvent) {
+        match event {
+            ExecutionEvent::NodeStarted {
+                node_id,
+                call_stack,
+                input,
+            } => self.lock().push(NewNodeExecution {
+                run_id: self.run_id.clone(),
+                node_id,
+                call_stack: to_json(&call_stack).unwrap_or_default(),
+                status: "running".to_string(),
+                attempts: 1,
+                input: to_json(&input),
+                output: None,
+                error: None,
+                started_at: now(),
+                finished_at: None,
+            }),
+            ExecutionEvent::NodeFinished {
+                node_id,
+                call_stack,
+                output,
+                ..
+            } => self.finish_node(&node_id, &call_stack, "succeeded", Some(&output), None),
+            ExecutionEvent::NodeFailed {
+                node_id,
+                call_stack,
+                error,
+                ..
+            } => self.finish_node(&node_id, &call_stack, "failed", None, Some(&error)),
+            ExecutionEvent::NodeRetrying {
+                node_id,
+                call_stack,
+                ..
+            } => self.update(&node_id, &call_stack, |execution| execution.attempts += 1),
+            ExecutionEvent::NodeCancelled {
+                node_id,
+                call_stack,
+                started,
+                ..
+            } => {
+                let error = RuntimeError::at(&node_id, RuntimeErrorKind::Cancelled);
+                if started {
+                    self.finish_node(&node_id, &call_stack, "cancelled", None, Some(&error));
+                } else {
+                    self.push_unstarted(node_id, &call_stack, "cancelled", Some(&error));
+                }
+            }
+            ExecutionEvent::NodeTimedOut {
+                node_id,
+                call_stack,
+                timeout_ms,
+                ..
+            } => {
+                let error = RuntimeError::at(&node_id, RuntimeErrorKind::TimedOut { timeout_ms });
+                self.finish_node(&node_id, &call_stack, "timedOut", None, Some(&error));
+            }
+            ExecutionEvent::NodeSkipped {
+                node_id,
+                call_stack,
+            } => self.push_unstarted(node_id, &call_stack, "skipped", None),
+            ExecutionEvent::NodePaused { .. } | ExecutionEvent::RunFinished { .. } => {}
+        }
+    }
+}
+
+fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+fn to_json(value: &(impl Serialize + ?Sized)) -> Option<String> {
+    serde_json::to_string(value).ok()
+}
+
+/// Selects past runs of the open project. Every field that is set has to match.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RunFilter {
+    run_ids: Option<Vec<String>>,
+    graph: Option<String>,
+    status: Option<String>,
+    /// Runs started at or after this time, in UTC.
+    since: Option<NaiveDateTime>,
+    /// Runs started before this time, in UTC.
+    until: Option<NaiveDateTime>,
+    /// Only used when listing; defaults to the 100 most recent runs.
+    limit: Option<i64>,
+}
+
+impl RunFilter {
+    fn query(&self, project_id: i32) -> run::BoxedQuery<'static, diesel::sqlite::Sqlite> {
+        let mut query = run::table
+            .filter(run::project_id.eq(project_id))
+            .into_boxed();
+
+        if let Some(run_ids) = &self.run_ids {
+            query = query.filter(run::id.eq_any(run_ids.clone()));
+        }
+        if let Some(graph) = &self.graph {
+            query = query.filter(run::graph.eq(graph.clone()));
+        }
+        if let Some(status) = &self.status {
+            query = query.filter(run::status.eq(status.clone()));
+        }
+        if let Some(since) = self.since {
+            query = query.filter(run::started_at.ge(since));
+        }
+        if let Some(until) = self.until {
+            query = query.filter(run::started_at.lt(until));
+        }
+
+        query
+    }
+}
+
+/// A past run with every node it executed, in the order they started.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RunDetails {
+    pub run: TrackedRun,
+    pub nodes: Vec<NodeExecution>,
+}
+
+pub(crate) async fn current_project_id(app: AppHandle) -> Result<i32, String> {
+    let location = retrieve_project_configuration(app).await?.location;
+
+    project::table
+        .filter(project::directory_location.eq(&location))
+        .select(project::id)
+        .first::<i32>(&mut *crate::state::get_connection())
+        .map_err(|e| format!("{location} is not a tracked project: {e}"))
+}
+
+/// Lists the past runs of the open project matching `filter`, most recent first.
+#[tauri::command]
+pub(crate) async fn list_runs(
+    app: AppHandle,
+    filter: Option<RunFilter>,
+) -> Result<Vec<TrackedRun>, String> {
+    let project_id = current_project_id(app).await?;
+    let filter = filter.unwrap_or_default();
+
+    filter
+        .query(project_id)
+        .order(run::started_at.desc())
+        .limit(filter.limit.unwrap_or(DEFAULT_RUN_LIMIT))
+        .select(TrackedRun::as_select())
+        .load(&mut *crate::state::get_connection())
+        .map_err(|e| format!("Failed to load runs: {e}"))
+}
+
+#[tauri::command]
+pub(crate) async fn get_run(app: AppHandle, run_id: String) -> Result<RunDetails, String> {
+    load_run(current_project_id(app).await?, &run_id)
+}
+
+pub(crate) fn load_run(project_id: i32, run_id: &str) -> Result<RunDetails, String> {
+    let mut connection = crate::state::get_connection();
+
+    let run = run::table
+        .filter(run::id.eq(run_id).and(run::project_id.eq(project_id)))
+        .select(TrackedRun::as_select())
+        .first(&mut *connection)
+        .optional()
+        .map_err(|e| format!("Failed to load run {run_id}: {e}"))?
+        .ok_or_else(|| format!("Run {run_id} does not exist."))?;
+
+    let nodes = node_execution::table
+        .filter(node_execution::run_id.eq(run_id))
+        .order(node_execution::id.asc())
+        .select(NodeExecution::as_select())
+        .load(&mut *connection)
+        .map_err(|e| format!("Failed to load the nodes of run {run_id}: {e}"))?;
+
+    Ok(RunDetails { run, nodes })
+}
+
+/// Deletes the past runs of the open project matching `filter`, returning how many were deleted.
+#[tauri::command]
+pub(crate) async fn delete_runs(app: AppHandle, filter: RunFilter) -> Result<usize, String> {
+    let project_id = current_project_id(app).await?;
+
+    crate::state::get_connection()
+        .transaction(|connection| {
+            let run_ids = filter
+                .query(project_id)
+                .select(run::id)
+                .load::<String>(connection)?;
+
+            // SQLite doesn't enforce the foreign keys unless asked to, so cascade by hand.
+            diesel::delete(node_execution::table.filter(node_execution::run_id.eq_any(&run_ids)))
+                .execute(connection)?;
+            diesel::delete(run::table.filter(run::id.eq_any(&run_ids))).execute(connection)
+        })
+        .map_err(|e: diesel::result::Error| format!("Failed to delete runs: {e}"))
+}
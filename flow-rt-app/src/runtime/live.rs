@@ -0,0 +1,298 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use flow_rt_shared::value::Value;
+use flow_rt_vm::{
+    debugger::SuspendReason,
+    error::RuntimeError,
+    interpreter::CallFrame,
+    observer::{ExecutionEvent, RunObserver},
+};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use uuid::Uuid;
+
+use crate::silence;
+
+/// Node outputs serializing to more than this many bytes are sent by reference.
+pub(crate) const MAX_INLINE_PAYLOAD: usize = 16 * 1024;
+
+/// Characters of a referenced payload included in the event as a preview.
+const PREVIEW_LENGTH: usize = 256;
+
+/// Referenced payloads kept around for the editor to fetch; the oldest are dropped first.
+const MAX_STORED_PAYLOADS: usize = 128;
+
+/// A node output as sent to the frontend.
+#[derive(Serialize, Clone, Debug)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub(crate) enum LivePayload {
+    Inline {
+        value: Value,
+    },
+    /// Too large to send along; fetch it with [`get_run_payload`].
+    Reference {
+        reference: String,
+        size: usize,
+        preview: String,
+    },
+}
+
+/// Holds the node outputs that were too large to send with their event.
+#[derive(Default)]
+pub(crate) struct PayloadStore {
+    payloads: Mutex<VecDeque<(String, Value)>>,
+}
+
+impl PayloadStore {
+    /// Keeps `value` inline when it is small enough, and stores it to be fetched otherwise.
+    pub(crate) fn shrink(&self, value: Value) -> LivePayload {
+        let serialized = serde_json::to_string(&value).unwrap_or_default();
+        if serialized.len() <= MAX_INLINE_PAYLOAD {
+            return LivePayload::Inline { value };
+        }
+
+        let reference = Uuid::new_v4().to_string();
+        let mut payloads = self.payloads.lock().unwrap_or_else(|e| e.into_inner());
+        if payloads.len() >= MAX_STORED_PAYLOADS {
+            payloads.pop_front();
+        }
+        payloads.push_back((reference.clone(), value));
+
+        LivePayload::Reference {
+            reference,
+            size: serialized.len(),
+            preview: serialized.chars().take(PREVIEW_LENGTH).collect(),
+        }
+    }
+
+    fn get(&self, reference: &str) -> Option<Value> {
+        self.payloads
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|(id, _)| id == reference)
+            .map(|(_, value)| value.clone())
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RunStarted<'a> {
+    run_id: &'a str,
+    graph: &'a str,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct NodeEvent {
+    run_id: String,
+    node_id: String,
+    call_stack: Vec<CallFrame>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<LivePayload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RuntimeError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<SuspendReason>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attempt: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    delay_ms: Option<u64>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RunFinished {
+    run_id: String,
+    error: Option<RuntimeError>,
+    duration_ms: u64,
+}
+
+/// Forwards the progress of one run to the frontend as Tauri events:
+/// `on_run_started`, `on_node_started`, `on_node_finished`, `on_node_failed`,
+/// `on_node_retrying`, `on_node_cancelled`, `on_node_timed_out`,
+/// `on_node_skipped`, `on_node_paused` and `on_run_finished`, all carrying
+/// the `runId`.
+pub(crate) struct LiveRun {
+    app: AppHandle,
+    run_id: String,
+}
+
+impl LiveRun {
+    /// Reports the progress of a new run under a fresh id. The frontend only
+    /// learns about the run once it is [announced](Self::announce).
+    pub(crate) fn new(app: AppHandle) -> Self {
+        Self {
+            app,
+            run_id: Uuid::new_v4().to_string(),
+        }
+    }
+
+    /// Tells the frontend the run of `graph` started.
+    pub(crate) fn announce(&self, graph: &str) {
+        silence!(self.app.emit(
+            "on_run_started",
+            RunStarted {
+                run_id: &self.run_id,
+                graph,
+            },
+        ));
+    }
+
+    pub(crate) fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    fn node_event(&self, node_id: String, call_stack: Vec<CallFrame>) -> NodeEvent {
+        NodeEvent {
+            run_id: self.run_id.clone(),
+            node_id,
+            call_stack,
+            output: None,
+            error: None,
+            duration_ms: None,
+            reason: None,
+            timeout_ms: None,
+            attempt: None,
+            delay_ms: None,
+        }
+    }
+}
+
+impl RunObserver for LiveRun {
+    fn notify(&self, event: ExecutionEvent) {
+        match event {
+            ExecutionEvent::NodeStarted {
+                node_id,
+                call_stack,
+                ..
+            } => {
+                silence!(self
+                    .app
+                    .emit("on_node_started", self.node_event(node_id, call_stack)));
+            }
+            ExecutionEvent::NodeFinished {
+                node_id,
+                call_stack,
+                output,
+                duration_ms,
+            } => {
+                let output = self.app.state::<PayloadStore>().shrink(output);
+                silence!(self.app.emit(
+                    "on_node_finished",
+                    NodeEvent {
+                        output: Some(output),
+                        duration_ms: Some(duration_ms),
+                        ..self.node_event(node_id, call_stack)
+                    },
+                ));
+            }
+            ExecutionEvent::NodeFailed {
+                node_id,
+                call_stack,
+                error,
+                duration_ms,
+            } => {
+                silence!(self.app.emit(
+                    "on_node_failed",
+                    NodeEvent {
+                        error: Some(error),
+                        duration_ms: Some(duration_ms),
+                        ..self.node_event(node_id, call_stack)
+                    },
+                ));
+            }
+            ExecutionEvent::NodeRetrying {
+                node_id,
+                call_stack,
+                attempt,
+                error,
+                delay_ms,
+            } => {
+                silence!(self.app.emit(
+                    "on_node_retrying",
+                    NodeEvent {
+                        error: Some(error),
+                        attempt: Some(attempt),
+                        delay_ms: Some(delay_ms),
+                        ..self.node_event(node_id, call_stack)
+                    },
+                ));
+            }
+            ExecutionEvent::NodeCancelled {
+                node_id,
+                call_stack,
+                duration_ms,
+                ..
+            } => {
+                silence!(self.app.emit(
+                    "on_node_cancelled",
+                    NodeEvent {
+                        duration_ms: Some(duration_ms),
+                        ..self.node_event(node_id, call_stack)
+                    },
+                ));
+            }
+            ExecutionEvent::NodeTimedOut {
+                node_id,
+                call_stack,
+                timeout_ms,
+                duration_ms,
+            } => {
+                silence!(self.app.emit(
+                    "on_node_timed_out",
+                    NodeEvent {
+                        timeout_ms: Some(timeout_ms),
+                        duration_ms: Some(duration_ms),
+                        ..self.node_event(node_id, call_stack)
+                    },
+                ));
+            }
+            ExecutionEvent::NodeSkipped {
+                node_id,
+                call_stack,
+            } => {
+                silence!(self
+                    .app
+                    .emit("on_node_skipped", self.node_event(node_id, call_stack)));
+            }
+            ExecutionEvent::NodePaused {
+                node_id,
+                call_stack,
+                reason,
+            } => {
+                silence!(self.app.emit(
+                    "on_node_paused",
+                    NodeEvent {
+                        reason: Some(reason),
+                        ..self.node_event(node_id, call_stack)
+                    },
+                ));
+            }
+            ExecutionEvent::RunFinished { error, duration_ms } => {
+                silence!(self.app.emit(
+                    "on_run_finished",
+                    RunFinished {
+                        run_id: self.run_id.clone(),
+                        error,
+                        duration_ms,
+                    },
+                ));
+            }
+        }
+    }
+}
+
+/// Returns a node output that was sent by reference in an `on_node_finished` event.
+#[tauri::command]
+pub(crate) async fn get_run_payload(app: AppHandle, reference: String) -> Option<Value> {
+    app.state::<PayloadStore>().get(&reference)
+}
@@ -0,0 +1,59 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use flow_rt_vm::memo::{project_cache_directory, DiskCache};
+use tauri::{async_runtime::spawn_blocking, AppHandle};
+
+use crate::settings::get_or_init_settings;
+
+/// The caches of pure function outputs of the projects opened so far, by
+/// project location. Kept for the lifetime of the app so a cache only scans
+/// its directory once.
+#[derive(Default)]
+pub(crate) struct OutputCaches {
+    caches: Mutex<HashMap<PathBuf, Arc<DiskCache>>>,
+}
+
+impl OutputCaches {
+    /// The cache of the project at `location`, limited to the size set in the app settings.
+    pub(crate) async fn for_project(
+        &self,
+        app: AppHandle,
+        location: &str,
+    ) -> Result<Arc<DiskCache>, String> {
+        let max_bytes = get_or_init_settings(app).await?.memo_cache_bytes();
+        let location = PathBuf::from(location);
+
+        // Opening and resizing a cache go through its whole directory, so
+        // both are kept off the async workers.
+        let opened = self.lock().get(&location).cloned();
+        if let Some(cache) = opened {
+            if cache.max_bytes() != max_bytes {
+                let resized = cache.clone();
+                spawn_blocking(move || resized.set_max_bytes(max_bytes))
+                    .await
+                    .map_err(|e| format!("Failed to resize the output cache: {e}"))?;
+            }
+            return Ok(cache);
+        }
+
+        let directory = project_cache_directory(&location);
+        let cache = spawn_blocking(move || {
+            DiskCache::open(&directory, max_bytes)
+                .map(Arc::new)
+                .map_err(|e| format!("Failed to open the output cache at {directory:?}: {e}"))
+        })
+        .await
+        .map_err(|e| format!("Failed to open the output cache: {e}"))??;
+
+        // Runs started together may both open the cache; they share the first one kept.
+        Ok(self.lock().entry(location).or_insert(cache).clone())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<PathBuf, Arc<DiskCache>>> {
+        self.caches.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,266 @@
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
+
+use flow_rt_shared::{schemas::FlowGraphPort, value::Value};
+use flow_rt_vm::{
+    cancellation::Cancellation,
+    compiler::FunctionCatalog,
+    debugger::Debugger,
+    error::RuntimeError,
+    events::{EventBus, EventRun, EventRunObserver},
+    interpreter::{Interpreter, RunOptions, RunOutput},
+    ir::Program,
+    observer::RunObserver,
+    typecheck::{self, Diagnostic, FunctionSignature},
+};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    binding::RegistryHost,
+    projects::graphs::retrieve_project_configuration,
+    runtime::{
+        cancel::RunCancellations, debug::DebugSessions, history::RunHistory, live::LiveRun,
+        memo::OutputCaches, reload::ProjectPrograms,
+    },
+    schemas::{helpers::JsonFile, FlowGraph},
+    silence,
+};
+
+pub(crate) mod cancel;
+pub(crate) mod debug;
+pub(crate) mod environment;
+pub(crate) mod history;
+pub(crate) mod live;
+pub(crate) mod memo;
+pub(crate) mod reload;
+pub(crate) mod replay;
+pub(crate) mod sandbox;
+
+pub(crate) fn graph_location(project_location: &str, name: &str) -> PathBuf {
+    PathBuf::from(project_location)
+        .join("flows")
+        .join(format!("{name}.jfg"))
+}
+
+#[tauri::command]
+pub(crate) async fn run_graph(
+    app: AppHandle,
+    name: String,
+    input: Option<Value>,
+    options: Option<RunOptions>,
+) -> Result<RunOutput, String> {
+    let project_config = retrieve_project_configuration(app.clone()).await?;
+    let (program, graphs) = pinned_program(&app, &name)?;
+
+    let input = input.unwrap_or_default();
+    let run = AttachedRun::start(&app, &name);
+    let run_id = run.run_id().to_string();
+
+    // A run that can't be recorded still runs; it's just missing from the history.
+    let history = RunHistory::start(&project_config.location, &run_id, &name, &program, &input)
+        .map(Arc::new)
+        .inspect_err(|e| eprintln!("Failed to record run {run_id}: {e}"))
+        .ok();
+
+    let bus = app.state::<Arc<EventBus>>().inner().clone();
+    let mut interpreter = Interpreter::new(program, Arc::new(RegistryHost))
+        .with_graphs(graphs)
+        .with_scripts(bus.scripts())
+        .with_events(bus.clone())
+        .with_options(options.unwrap_or_default());
+    if let Some(secrets) = bus.secrets() {
+        interpreter = interpreter.with_secrets(secrets);
+    }
+    if let Some(environment) = bus.environment() {
+        interpreter = interpreter.with_environment(environment);
+    }
+
+    // Without a cache, pure functions are called every time like any other.
+    match app
+        .state::<OutputCaches>()
+        .for_project(app.clone(), &project_config.location)
+        .await
+    {
+        Ok(cache) => interpreter = interpreter.with_cache(cache),
+        Err(e) => eprintln!("{e}"),
+    }
+
+    run_attached(run, history, interpreter, input)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// The current version of `graph` along with every other graph of the
+/// project, pinned for a whole run so reloads only affect the runs started
+/// after them.
+pub(crate) fn pinned_program(
+    app: &AppHandle,
+    graph: &str,
+) -> Result<(Arc<Program>, Arc<BTreeMap<String, Arc<Program>>>), String> {
+    let programs = app.state::<ProjectPrograms>();
+    let graphs = programs.snapshot();
+    let program = match graphs.get(graph) {
+        Some(program) => program.clone(),
+        None => programs.get(graph)?,
+    };
+
+    Ok((program, graphs))
+}
+
+/// A run the editor is attached to. It can be debugged and cancelled from
+/// the moment it is announced, as the frontend may act on its id right away,
+/// until it is dropped.
+pub(crate) struct AttachedRun {
+    app: AppHandle,
+    live: Arc<LiveRun>,
+    debugger: Arc<Debugger>,
+    cancellation: Arc<Cancellation>,
+}
+
+impl AttachedRun {
+    /// Registers a new run of `graph`, then announces it to the frontend.
+    pub(crate) fn start(app: &AppHandle, graph: &str) -> Self {
+        let live = LiveRun::new(app.clone());
+        let debugger = app.state::<DebugSessions>().start(live.run_id());
+        let cancellation = app.state::<RunCancellations>().start(live.run_id());
+        live.announce(graph);
+
+        Self {
+            app: app.clone(),
+            live: Arc::new(live),
+            debugger,
+            cancellation,
+        }
+    }
+
+    pub(crate) fn run_id(&self) -> &str {
+        self.live.run_id()
+    }
+}
+
+impl Drop for AttachedRun {
+    fn drop(&mut self) {
+        let run_id = self.live.run_id();
+        self.app.state::<DebugSessions>().finish(run_id);
+        self.app.state::<RunCancellations>().finish(run_id);
+    }
+}
+
+/// Runs `interpreter` as `run`: progress is reported under its id, it can be
+/// debugged and cancelled, and it is recorded in `history` when given one.
+pub(crate) async fn run_attached(
+    run: AttachedRun,
+    history: Option<Arc<RunHistory>>,
+    interpreter: Interpreter,
+    input: Value,
+) -> Result<RunOutput, RuntimeError> {
+    let run_id = run.run_id().to_string();
+
+    let mut observers: Vec<Arc<dyn RunObserver>> = vec![run.live.clone()];
+    if let Some(history) = &history {
+        observers.push(history.clone());
+    }
+
+    let result = interpreter
+        .with_observer(Arc::new(observers))
+        .with_debugger(run.debugger.clone())
+        .with_cancellation(run.cancellation.clone())
+        .run(input)
+        .await;
+
+    drop(run);
+    if let Some(Err(e)) = history.map(|history| history.finish(&result)) {
+        eprintln!("Failed to record run {run_id}: {e}");
+    }
+
+    result
+}
+
+/// The plugin functions of [`RegistryHost`] along with the function graphs
+/// saved in the open project, so graph calls are checked too.
+struct ProjectCatalog {
+    project_location: Option<String>,
+}
+
+impl FunctionCatalog for ProjectCatalog {
+    fn contains(&self, plugin: &str, function: &str) -> bool {
+        RegistryHost.contains(plugin, function)
+    }
+
+    fn signature(&self, plugin: &str, function: &str) -> Option<FunctionSignature> {
+        RegistryHost.signature(plugin, function)
+    }
+
+    fn is_pure(&self, plugin: &str, function: &str) -> bool {
+        RegistryHost.is_pure(plugin, function)
+    }
+
+    fn graph_schema(&self, graph: &str) -> Option<Vec<FlowGraphPort>> {
+        let location = graph_location(self.project_location.as_deref()?, graph);
+        typecheck::graph_schema(&FlowGraph::from_json_file(&location).ok()?)
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn check_graph(app: AppHandle, graph: FlowGraph) -> Vec<Diagnostic> {
+    let catalog = ProjectCatalog {
+        project_location: retrieve_project_configuration(app)
+            .await
+            .ok()
+            .map(|config| config.location),
+    };
+    typecheck::check(&graph, &catalog)
+}
+
+/// Fires `event`, starting every event graph of the open project that listens for it.
+#[tauri::command]
+pub(crate) async fn fire_event(
+    app: AppHandle,
+    event: String,
+    payload: Option<Value>,
+) -> Result<Vec<EventRun>, String> {
+    retrieve_project_configuration(app.clone()).await?;
+
+    let bus = app.state::<Arc<EventBus>>().inner().clone();
+    Ok(bus.fire(&event, payload.unwrap_or_default()).await)
+}
+
+/// Sent to the frontend as `on_event_run_failed` when an event graph started
+/// by an `eventTrigger` node fails.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EventRunFailed {
+    event: String,
+    graph: String,
+    error: RuntimeError,
+}
+
+/// Reports the event graphs that `eventTrigger` nodes start and that fail,
+/// as nothing else waits for their outcome.
+pub(crate) struct EventRunReporter {
+    app: AppHandle,
+}
+
+impl EventRunReporter {
+    pub(crate) fn new(app: AppHandle) -> Self {
+        Self { app }
+    }
+}
+
+impl EventRunObserver for EventRunReporter {
+    fn finished(&self, event: &str, run: &EventRun) {
+        let Err(error) = &run.result else {
+            return;
+        };
+
+        eprintln!("Event graph {} failed on {event}: {error}", run.graph);
+        silence!(self.app.emit(
+            "on_event_run_failed",
+            EventRunFailed {
+                event: event.to_string(),
+                graph: run.graph.clone(),
+                error: error.clone(),
+            },
+        ));
+    }
+}
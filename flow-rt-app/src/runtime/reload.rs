@@ -0,0 +1,258 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+use flow_rt_vm::{
+    compiler::compile_with_scripts, events::EventBus, ir::Program, script::ScriptDirectory,
+};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    binding::RegistryHost,
+    runtime::graph_location,
+    schemas::{helpers::JsonFile, FlowGraph},
+    silence,
+};
+
+/// How long to wait for more file changes before recompiling, as editors
+/// often save a file in several steps.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// The compiled graphs of the open project, by name.
+///
+/// Reloading a graph swaps in a new map instead of changing the current one,
+/// so a run that took a [`ProjectPrograms::snapshot`] keeps executing the
+/// versions it started with, including the function graphs it calls. Event
+/// graphs get a snapshot from the [`EventBus`] whenever they start.
+#[derive(Default)]
+pub(crate) struct ProjectPrograms {
+    programs: RwLock<Arc<BTreeMap<String, Arc<Program>>>>,
+    errors: RwLock<BTreeMap<String, String>>,
+}
+
+impl ProjectPrograms {
+    pub(crate) fn snapshot(&self) -> Arc<BTreeMap<String, Arc<Program>>> {
+        self.programs
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// The current version of `graph`, or the reason it couldn't be compiled.
+    pub(crate) fn get(&self, graph: &str) -> Result<Arc<Program>, String> {
+        if let Some(program) = self.snapshot().get(graph) {
+            return Ok(program.clone());
+        }
+
+        match self
+            .errors
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(graph)
+        {
+            Some(error) => Err(format!("Graph {graph} failed to compile: {error}")),
+            None => Err(format!("Graph {graph} does not exist.")),
+        }
+    }
+
+    fn update(&self, graph: &str, program: Option<Result<Program, String>>) {
+        let mut programs = self.programs.write().unwrap_or_else(|e| e.into_inner());
+        let mut errors = self.errors.write().unwrap_or_else(|e| e.into_inner());
+
+        let mut next = programs.as_ref().clone();
+        next.remove(graph);
+        errors.remove(graph);
+
+        match program {
+            Some(Ok(program)) => {
+                next.insert(graph.to_string(), Arc::new(program));
+            }
+            Some(Err(error)) => {
+                errors.insert(graph.to_string(), error);
+            }
+            None => {}
+        }
+
+        *programs = Arc::new(next);
+    }
+
+    fn clear(&self) {
+        *self.programs.write().unwrap_or_else(|e| e.into_inner()) = Arc::default();
+        self.errors
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+}
+
+/// Sent to the frontend as `on_graph_reloaded` whenever a graph is recompiled.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct GraphReloaded {
+    graph: String,
+    /// The graph file was deleted; the graph can no longer be run.
+    removed: bool,
+    /// Why the new version couldn't be compiled. New runs of the graph fail until it is fixed.
+    error: Option<String>,
+    /// The node the compile error points at, if any.
+    node_id: Option<String>,
+}
+
+/// Compiles every graph of the project at `project_location`, replacing the
+/// graphs of the previously open project.
+pub(crate) fn load_project_graphs(app: &AppHandle, project_location: &str) {
+    app.state::<ProjectPrograms>().clear();
+    let bus = app.state::<Arc<EventBus>>();
+    bus.clear();
+    bus.set_functions(Arc::default());
+
+    for graph in graph_names(project_location) {
+        reload_graph(app, project_location, &graph);
+    }
+}
+
+/// Recompiles `graph` from disk, so new runs use the new version, and tells
+/// the frontend about it.
+pub(crate) fn reload_graph(app: &AppHandle, project_location: &str, graph: &str) {
+    let location = graph_location(project_location, graph);
+    let mut reloaded = GraphReloaded {
+        graph: graph.to_string(),
+        removed: !location.exists(),
+        error: None,
+        node_id: None,
+    };
+
+    let program = (!reloaded.removed).then(|| {
+        FlowGraph::from_json_file(&location).and_then(|flow_graph| {
+            let scripts = ScriptDirectory::of_project(Path::new(project_location));
+            compile_with_scripts(&flow_graph, &RegistryHost, &scripts).map_err(|e| {
+                reloaded.node_id = e.node_id.clone();
+                e.kind.to_string()
+            })
+        })
+    });
+
+    let bus = app.state::<Arc<EventBus>>();
+    bus.unregister(graph);
+
+    match &program {
+        Some(Ok(program)) if program.events().next().is_some() => {
+            bus.register(graph, Arc::new(program.clone()));
+        }
+        Some(Err(error)) => reloaded.error = Some(error.clone()),
+        _ => {}
+    }
+
+    let programs = app.state::<ProjectPrograms>();
+    programs.update(graph, program);
+    bus.set_functions(programs.snapshot());
+    silence!(app.emit("on_graph_reloaded", reloaded));
+}
+
+fn graph_names(project_location: &str) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(PathBuf::from(project_location).join("flows")) else {
+        return vec![];
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| graph_name(&entry.path()))
+        .collect()
+}
+
+fn graph_name(path: &Path) -> Option<String> {
+    if path.extension().is_some_and(|ext| ext == "jfg") {
+        path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(str::to_string)
+    } else {
+        None
+    }
+}
+
+/// The file watcher of the open project. Replacing it stops the previous one.
+#[derive(Default)]
+pub(crate) struct ProjectWatcher(Mutex<Option<RecommendedWatcher>>);
+
+/// Watches the `flows` and `scripts` directories of the project at
+/// `project_location` and recompiles the graphs affected by every change.
+///
+/// A changed graph file reloads that graph. A changed script reloads every
+/// graph, as any of them may use it.
+pub(crate) fn watch_project(app: &AppHandle, project_location: &str) -> Result<(), String> {
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel::<PathBuf>();
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+
+        if matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        ) {
+            for path in event.paths {
+                silence!(sender.send(path));
+            }
+        }
+    })
+    .map_err(|e| format!("Failed to start watching the project: {e}"))?;
+
+    let flows = PathBuf::from(project_location).join("flows");
+    let scripts = PathBuf::from(project_location).join("scripts");
+    for directory in [flows.clone(), scripts.clone()] {
+        if directory.is_dir() {
+            watcher
+                .watch(&directory, RecursiveMode::Recursive)
+                .map_err(|e| format!("Failed to watch {directory:?}: {e}"))?;
+        }
+    }
+
+    let app_handle = app.clone();
+    let project_location = project_location.to_string();
+
+    // Ends once the watcher, and with it the sender, is dropped.
+    tauri::async_runtime::spawn(async move {
+        while let Some(first) = receiver.recv().await {
+            tokio::time::sleep(RELOAD_DEBOUNCE).await;
+
+            let mut changed = BTreeSet::from([first]);
+            while let Ok(path) = receiver.try_recv() {
+                changed.insert(path);
+            }
+
+            let graphs = if changed.iter().any(|path| path.starts_with(&scripts)) {
+                app_handle
+                    .state::<ProjectPrograms>()
+                    .snapshot()
+                    .keys()
+                    .cloned()
+                    .chain(graph_names(&project_location))
+                    .collect::<BTreeSet<_>>()
+            } else {
+                // Graphs are only read from `flows` itself, like `graph_names` does.
+                changed
+                    .iter()
+                    .filter(|path| path.parent() == Some(flows.as_path()))
+                    .filter_map(|path| graph_name(path))
+                    .collect()
+            };
+
+            for graph in graphs {
+                reload_graph(&app_handle, &project_location, &graph);
+            }
+        }
+    });
+
+    *app.state::<ProjectWatcher>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner()) = Some(watcher);
+
+    Ok(())
+}
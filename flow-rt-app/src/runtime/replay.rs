@@ -0,0 +1,164 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use flow_rt_shared::value::Value;
+use flow_rt_vm::{
+    error::RuntimeError,
+    events::EventBus,
+    interpreter::{CallFrame, Interpreter, RunOptions, RunOutput},
+    ir::{Op, Program},
+    replay::Replay,
+};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    binding::RegistryHost,
+    runtime::{
+        history::{current_project_id, load_run, RunDetails},
+        pinned_program, run_attached, AttachedRun,
+    },
+    silence,
+};
+
+/// How a replay went. A replay reproducing a failure ends with that failure
+/// in `error`, which is still a successful replay.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReplayOutcome {
+    /// Id of the replay in the `on_run_*` and `on_node_*` events.
+    run_id: String,
+    /// Reasons the replay may not match the recording, also sent as `on_replay_warning`.
+    warnings: Vec<String>,
+    output: Option<RunOutput>,
+    error: Option<RuntimeError>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ReplayWarning<'a> {
+    run_id: &'a str,
+    message: &'a str,
+}
+
+/// Runs the graph of the recorded run `run_id` again with the same input,
+/// answering every plugin call with its recorded output or error instead of
+/// calling the plugin. The replay can be debugged like any other run.
+#[tauri::command]
+pub(crate) async fn replay_run(
+    app: AppHandle,
+    run_id: String,
+    options: Option<RunOptions>,
+) -> Result<ReplayOutcome, String> {
+    let recorded = load_run(current_project_id(app.clone()).await?, &run_id)?;
+    let (program, graphs) = pinned_program(&app, &recorded.run.graph)?;
+
+    let mut warnings = vec![];
+    match &recorded.run.graph_hash {
+        Some(hash) if *hash != program.fingerprint() => warnings.push(format!(
+            "Graph {} changed since run {run_id} was recorded; the replay may not match it.",
+            recorded.run.graph
+        )),
+        Some(_) => {}
+        None => warnings.push(format!(
+            "Run {run_id} was recorded without a fingerprint of graph {}; changes to it can't be detected.",
+            recorded.run.graph
+        )),
+    }
+    if recorded.run.status == "running" {
+        warnings.push(format!(
+            "Run {run_id} never finished; calls it didn't record will fail."
+        ));
+    }
+
+    let replay = recording(&recorded, &program, &graphs, &mut warnings);
+    let input = recorded
+        .run
+        .input
+        .as_deref()
+        .and_then(|input| serde_json::from_str::<Value>(input).ok())
+        .unwrap_or_default();
+
+    let run = AttachedRun::start(&app, &recorded.run.graph);
+    let replay_id = run.run_id().to_string();
+    for message in &warnings {
+        silence!(app.emit(
+            "on_replay_warning",
+            ReplayWarning {
+                run_id: &replay_id,
+                message,
+            },
+        ));
+    }
+
+    let bus = app.state::<Arc<EventBus>>().inner().clone();
+    let mut interpreter = Interpreter::new(program, Arc::new(RegistryHost))
+        .with_graphs(graphs)
+        .with_scripts(bus.scripts())
+        .with_events(bus.clone())
+        .with_replay(Arc::new(replay))
+        .with_options(options.unwrap_or_default());
+    if let Some(secrets) = bus.secrets() {
+        interpreter = interpreter.with_secrets(secrets);
+    }
+    if let Some(environment) = bus.environment() {
+        interpreter = interpreter.with_environment(environment);
+    }
+
+    // Replays aren't recorded themselves, the recording is the original run.
+    let result = run_attached(run, None, interpreter, input).await;
+
+    Ok(ReplayOutcome {
+        run_id: replay_id,
+        warnings,
+        output: result.as_ref().ok().cloned(),
+        error: result.err(),
+    })
+}
+
+/// The outcome of every plugin call of `recorded` that finished, in the
+/// order they started. Other nodes run again during the replay.
+fn recording(
+    recorded: &RunDetails,
+    program: &Program,
+    graphs: &BTreeMap<String, Arc<Program>>,
+    warnings: &mut Vec<String>,
+) -> Replay {
+    let replay = Replay::new();
+
+    for node in &recorded.nodes {
+        let outcome = match node.status.as_str() {
+            "succeeded" => node
+                .output
+                .as_deref()
+                .map(|output| serde_json::from_str::<Value>(output).map(Ok)),
+            "failed" | "timedOut" | "cancelled" => node
+                .error
+                .as_deref()
+                .map(|error| serde_json::from_str::<RuntimeError>(error).map(Err)),
+            _ => continue,
+        };
+
+        let call_stack = serde_json::from_str::<Vec<CallFrame>>(&node.call_stack);
+        match (outcome, call_stack) {
+            (Some(Ok(outcome)), Ok(call_stack)) => {
+                // Nodes of a function graph belong to the graph called last.
+                let owner = match call_stack.last() {
+                    Some(frame) => graphs.get(&frame.graph).map(Arc::as_ref),
+                    None => Some(program),
+                };
+                let is_call = owner
+                    .and_then(|owner| owner.instruction_by_node_id(&node.node_id))
+                    .is_some_and(|instruction| matches!(instruction.op, Op::Call { .. }));
+                if is_call {
+                    replay.record(call_stack, &node.node_id, outcome);
+                }
+            }
+            _ => warnings.push(format!(
+                "The recorded outcome of node {} can't be read and is left out.",
+                node.node_id
+            )),
+        }
+    }
+
+    replay
+}
@@ -0,0 +1,34 @@
+use std::{path::PathBuf, sync::Arc};
+
+use flow_rt_vm::{
+    events::EventBus,
+    script::{ScriptEngine, ScriptSandbox},
+};
+use tauri::{AppHandle, Manager};
+
+use crate::{projects::toml_from_project_location, settings::get_or_init_settings};
+
+/// The sandbox scripts of the project at `project_location` run in: the
+/// `[scripts]` table of its `Flow.toml`, or the one from the app settings.
+pub(crate) async fn project_sandbox(app: &AppHandle, project_location: &str) -> ScriptSandbox {
+    let toml_location = PathBuf::from(project_location).join("Flow.toml");
+    if let Some(sandbox) = toml_from_project_location(&toml_location)
+        .ok()
+        .and_then(|configuration| configuration.scripts)
+    {
+        return sandbox;
+    }
+
+    get_or_init_settings(app.clone())
+        .await
+        .map(|settings| settings.script_sandbox().clone())
+        .unwrap_or_default()
+}
+
+/// Runs the scripts of the project at `project_location` in its sandbox from
+/// now on, both in graphs started from the editor and in event graphs.
+pub(crate) async fn configure_scripts(app: &AppHandle, project_location: &str) {
+    let sandbox = project_sandbox(app, project_location).await;
+    app.state::<Arc<EventBus>>()
+        .set_scripts(Arc::new(ScriptEngine::new(sandbox)));
+}
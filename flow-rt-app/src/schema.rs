@@ -1,5 +1,21 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    node_execution (id) {
+        id -> Integer,
+        run_id -> Text,
+        node_id -> Text,
+        call_stack -> Text,
+        status -> Text,
+        attempts -> Integer,
+        input -> Nullable<Text>,
+        output -> Nullable<Text>,
+        error -> Nullable<Text>,
+        started_at -> Timestamp,
+        finished_at -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     project (id) {
         id -> Integer,
@@ -16,4 +32,22 @@ diesel::table! {
     }
 }
 
-diesel::allow_tables_to_appear_in_same_query!(project, recent_project,);
+diesel::table! {
+    run (id) {
+        id -> Text,
+        project_id -> Integer,
+        graph -> Text,
+        status -> Text,
+        input -> Nullable<Text>,
+        output -> Nullable<Text>,
+        error -> Nullable<Text>,
+        started_at -> Timestamp,
+        finished_at -> Nullable<Timestamp>,
+        graph_hash -> Nullable<Text>,
+    }
+}
+
+diesel::joinable!(node_execution -> run (run_id));
+diesel::joinable!(run -> project (project_id));
+
+diesel::allow_tables_to_appear_in_same_query!(node_execution, project, recent_project, run,);
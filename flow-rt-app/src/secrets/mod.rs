@@ -0,0 +1,215 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use flow_rt_vm::{events::EventBus, secrets::SecretStore};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::{fs::get_or_init_settings_path, projects::graphs::retrieve_project_configuration};
+
+/// The file under the settings directory holding the key every project's secrets are encrypted with.
+const KEY_FILE: &str = "secrets.key";
+const NONCE_LENGTH: usize = 12;
+
+/// Where the encrypted secrets of the project at `project` are stored.
+pub(crate) fn project_secrets_file(project: &Path) -> PathBuf {
+    project.join(".flow").join("secrets.json")
+}
+
+/// The secrets of a project, as stored on disk: every value is encrypted on
+/// its own and hex encoded as nonce followed by ciphertext.
+#[derive(Serialize, Deserialize, Default)]
+struct SecretsFile {
+    secrets: BTreeMap<String, String>,
+}
+
+/// The encrypted secrets store of one project.
+///
+/// Values are encrypted with AES-256-GCM, bound to their name, using a key
+/// kept in `~/.config/flow-rt-app` so the store can sit next to the
+/// project without giving its secrets away. The key is created the first
+/// time a secret is added.
+pub(crate) struct ProjectSecrets {
+    file: PathBuf,
+}
+
+impl ProjectSecrets {
+    pub(crate) fn new(project_location: &str) -> Self {
+        Self {
+            file: project_secrets_file(Path::new(project_location)),
+        }
+    }
+
+    pub(crate) fn names(&self) -> Result<Vec<String>, String> {
+        Ok(self.read()?.secrets.into_keys().collect())
+    }
+
+    pub(crate) fn add(&self, name: &str, value: &str) -> Result<(), String> {
+        check_name(name)?;
+        let cipher = Aes256Gcm::new(&load_key(true)?);
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: value.as_bytes(),
+                    aad: name.as_bytes(),
+                },
+            )
+            .map_err(|_| format!("Failed to encrypt secret {name}"))?;
+
+        let mut file = self.read()?;
+        file.secrets.insert(
+            name.to_string(),
+            hex::encode([nonce.as_slice(), &ciphertext].concat()),
+        );
+        self.write(&file)
+    }
+
+    /// Deletes the secret `name`. Returns whether there was one.
+    pub(crate) fn delete(&self, name: &str) -> Result<bool, String> {
+        let mut file = self.read()?;
+        let deleted = file.secrets.remove(name).is_some();
+        if deleted {
+            self.write(&file)?;
+        }
+        Ok(deleted)
+    }
+
+    fn decrypt(&self, name: &str) -> Result<Option<String>, String> {
+        let Some(encoded) = self.read()?.secrets.remove(name) else {
+            return Ok(None);
+        };
+
+        let bytes = hex::decode(encoded).map_err(|e| format!("Secret {name} is corrupt: {e}"))?;
+        if bytes.len() < NONCE_LENGTH {
+            return Err(format!("Secret {name} is corrupt"));
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LENGTH);
+
+        let cipher = Aes256Gcm::new(&load_key(false)?);
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: name.as_bytes(),
+                },
+            )
+            .map_err(|_| format!("Secret {name} can't be decrypted with the current key"))?;
+
+        String::from_utf8(plaintext)
+            .map(Some)
+            .map_err(|_| format!("Secret {name} is corrupt"))
+    }
+
+    fn read(&self) -> Result<SecretsFile, String> {
+        match fs::read(&self.file) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map_err(|e| format!("Failed to read secrets at {:?}: {e}", self.file)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SecretsFile::default()),
+            Err(e) => Err(format!("Failed to read secrets at {:?}: {e}", self.file)),
+        }
+    }
+
+    fn write(&self, file: &SecretsFile) -> Result<(), String> {
+        let contents = serde_json::to_vec_pretty(file).map_err(|e| e.to_string())?;
+        self.file
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&self.file, contents))
+            .map_err(|e| format!("Failed to write secrets at {:?}: {e}", self.file))
+    }
+}
+
+impl SecretStore for ProjectSecrets {
+    fn secret(&self, name: &str) -> Option<String> {
+        self.decrypt(name)
+            .inspect_err(|e| eprintln!("{e}"))
+            .ok()
+            .flatten()
+    }
+}
+
+/// Secret names end up in `${secret.NAME}` references, so they are kept to
+/// characters that can't end one early.
+fn check_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Secret names may only contain letters, digits, `_`, `-` and `.`, got {name:?}"
+        ))
+    }
+}
+
+/// The key secrets are encrypted with, created first when `create` is set.
+fn load_key(create: bool) -> Result<Key<Aes256Gcm>, String> {
+    let directory = get_or_init_settings_path()
+        .ok_or_else(|| "Failed to find the settings directory".to_string())?;
+    let path = directory.join(KEY_FILE);
+
+    match fs::read(&path) {
+        Ok(bytes) if bytes.len() == 32 => Ok(*Key::<Aes256Gcm>::from_slice(&bytes)),
+        Ok(_) => Err(format!("The secrets key at {path:?} is corrupt")),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && create => {
+            let key = Aes256Gcm::generate_key(OsRng);
+            write_key(&path, &key)
+                .map_err(|e| format!("Failed to store the secrets key at {path:?}: {e}"))?;
+            Ok(key)
+        }
+        Err(e) => Err(format!("Failed to read the secrets key at {path:?}: {e}")),
+    }
+}
+
+fn write_key(path: &Path, key: &Key<Aes256Gcm>) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(path)?.write_all(key.as_slice())
+}
+
+/// Resolves the secrets referenced by the graphs of the project at
+/// `project_location` from its store from now on.
+pub(crate) fn configure_secrets(app: &AppHandle, project_location: &str) {
+    app.state::<Arc<EventBus>>()
+        .set_secrets(Arc::new(ProjectSecrets::new(project_location)));
+}
+
+#[tauri::command]
+pub(crate) async fn add_secret(app: AppHandle, name: String, value: String) -> Result<(), String> {
+    let project_config = retrieve_project_configuration(app).await?;
+    ProjectSecrets::new(&project_config.location).add(&name, &value)
+}
+
+/// Lists the names of the secrets of the open project. Values never leave the backend.
+#[tauri::command]
+pub(crate) async fn list_secrets(app: AppHandle) -> Result<Vec<String>, String> {
+    let project_config = retrieve_project_configuration(app).await?;
+    ProjectSecrets::new(&project_config.location).names()
+}
+
+#[tauri::command]
+pub(crate) async fn delete_secret(app: AppHandle, name: String) -> Result<bool, String> {
+    let project_config = retrieve_project_configuration(app).await?;
+    ProjectSecrets::new(&project_config.location).delete(&name)
+}
@@ -1,5 +1,6 @@
 use std::path::PathBuf;
 
+use flow_rt_vm::script::ScriptSandbox;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::from_str;
 use tauri::{AppHandle, Manager};
@@ -63,11 +64,21 @@ fn theme_mode_default() -> String {
     THEME_MODE_DEFAULT.to_owned()
 }
 
+fn memo_cache_bytes_default() -> u64 {
+    flow_rt_vm::memo::DEFAULT_CACHE_BYTES
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AppSettings {
     version: Version,
     #[serde(default = "theme_mode_default", rename = "themeMode")]
     theme_mode: String,
+    /// How large the cache of pure function outputs of a project may grow.
+    #[serde(default = "memo_cache_bytes_default", rename = "memoCacheBytes")]
+    memo_cache_bytes: u64,
+    /// What scripts may do in projects whose `Flow.toml` has no `[scripts]` table.
+    #[serde(default, rename = "scriptSandbox")]
+    script_sandbox: ScriptSandbox,
 }
 
 impl Default for AppSettings {
@@ -75,10 +86,22 @@ impl Default for AppSettings {
         Self {
             version: Version::default(),
             theme_mode: "system".to_owned(),
+            memo_cache_bytes: memo_cache_bytes_default(),
+            script_sandbox: ScriptSandbox::default(),
         }
     }
 }
 
+impl AppSettings {
+    pub fn memo_cache_bytes(&self) -> u64 {
+        self.memo_cache_bytes
+    }
+
+    pub fn script_sandbox(&self) -> &ScriptSandbox {
+        &self.script_sandbox
+    }
+}
+
 #[tauri::command]
 pub async fn get_or_init_settings(app: AppHandle) -> Result<AppSettings, String> {
     if let Some(state) = app.try_state::<AppSettingsState>() {
@@ -0,0 +1,2 @@
+pub mod schemas;
+pub mod value;
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+use crate::value::ValueType;
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct FileReference {
+    pub full_path: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct FlowProject {
+    pub flow_graphs: Vec<FlowGraph>,
+    pub file_reference: Option<FileReference>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct FlowGraph {
+    pub nodes: Option<Vec<FlowGraphNode>>,
+    pub edges: Option<Vec<FlowGraphEdge>>,
+    pub viewport: Option<Viewport>,
+    pub file_reference: Option<FileReference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variables: Option<Vec<FlowGraphVariable>>,
+}
+
+/// A variable of a graph, read by `getVariable` and written by `setVariable` nodes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FlowGraphVariable {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub value_type: ValueType,
+    /// The value the variable holds when a run, or a call of the function graph, starts.
+    #[serde(default)]
+    pub default: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct FlowGraphNode {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub node_type: String,
+    pub position: Position,
+    #[serde(rename = "measured", default)]
+    pub measurement: Measurement,
+    pub data: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct FlowGraphEdge {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub edge_type: String,
+    pub source: String,
+    pub target: String,
+    /// Output port of `source` the edge leaves from. Absent in graphs saved before ports existed.
+    #[serde(
+        rename = "sourceHandle",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub source_handle: Option<String>,
+    /// Input port of `target` the edge feeds into. Absent in graphs saved before ports existed.
+    #[serde(
+        rename = "targetHandle",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub target_handle: Option<String>,
+}
+
+/// A named, typed input or output of a node, declared in the node `data` under
+/// `inputs` or `outputs`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FlowGraphPort {
+    pub id: String,
+    #[serde(rename = "type", default)]
+    pub value_type: ValueType,
+    #[serde(default)]
+    pub required: bool,
+}
+
+impl FlowGraphNode {
+    pub fn input_ports(&self) -> Vec<FlowGraphPort> {
+        self.ports("inputs")
+    }
+
+    pub fn output_ports(&self) -> Vec<FlowGraphPort> {
+        self.ports("outputs")
+    }
+
+    fn ports(&self, field: &str) -> Vec<FlowGraphPort> {
+        self.data
+            .get(field)
+            .and_then(|ports| serde_json::from_value(ports.clone()).ok())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct Measurement {
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub zoom: f32,
+}
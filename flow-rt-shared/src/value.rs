@@ -0,0 +1,529 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, ser::SerializeMap};
+
+/// Key used to mark a JSON object as an encoded [`Value::Bytes`].
+const BYTES_MARKER: &str = "$bytes";
+
+/// Returns whether `key` is the bytes marker with one or more extra leading
+/// `$`, which is how a single-key object that would collide with the marker
+/// is escaped.
+fn is_escaped_marker(key: &str) -> bool {
+    key.strip_prefix('$') == Some(BYTES_MARKER)
+        || key.starts_with("$$") && is_escaped_marker(&key[1..])
+}
+
+/// Adds or removes the escape of a single-key object whose key collides with
+/// [`BYTES_MARKER`], so `{ "$bytes": .. }` written by a user never reads back
+/// as bytes.
+fn escape_marker_key(key: &str, escape: bool) -> Option<String> {
+    match (escape, key) {
+        (true, key) if key == BYTES_MARKER || is_escaped_marker(key) => Some(format!("${key}")),
+        (false, key) if is_escaped_marker(key) => Some(key[1..].to_string()),
+        _ => None,
+    }
+}
+
+/// A value flowing through a graph at runtime.
+///
+/// Serializes to plain JSON so plugins and the editor can exchange values with
+/// the runtime. Bytes are encoded as `{ "$bytes": "<base64>" }`; an object
+/// with a single `$bytes` key of its own is written as `{ "$$bytes": .. }`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Value {
+    #[default]
+    Null,
+    Bool(bool),
+    Number(serde_json::Number),
+    String(String),
+    Bytes(Vec<u8>),
+    Array(Vec<Value>),
+    Object(BTreeMap<String, Value>),
+}
+
+/// The type of a [`Value`], as used by handles in the editor.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueType {
+    Null,
+    #[serde(alias = "boolean")]
+    Bool,
+    Number,
+    String,
+    Bytes,
+    Array,
+    Object,
+    /// Accepts every value without conversion.
+    #[default]
+    Any,
+}
+
+/// How a value of one type can be turned into another.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum Coercion {
+    /// The types are the same, the value is passed as is.
+    Exact,
+    /// Conversion always succeeds, e.g. a number into a string.
+    Lossless,
+    /// Conversion depends on the value, e.g. a string into a number.
+    Fallible,
+    /// Conversion never succeeds.
+    Impossible,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CoercionError {
+    pub from: ValueType,
+    pub to: ValueType,
+    pub reason: String,
+}
+
+impl Value {
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Value::Null => ValueType::Null,
+            Value::Bool(_) => ValueType::Bool,
+            Value::Number(_) => ValueType::Number,
+            Value::String(_) => ValueType::String,
+            Value::Bytes(_) => ValueType::Bytes,
+            Value::Array(_) => ValueType::Array,
+            Value::Object(_) => ValueType::Object,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => n.as_f64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Number(n) => n.as_i64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::Number(n) => n.as_u64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&BTreeMap<String, Value>> {
+        match self {
+            Value::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    /// Looks up `key` when this value is an object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_object().and_then(|o| o.get(key))
+    }
+
+    /// Converts this value into `target`, following the rules described by
+    /// [`ValueType::coercion_to`].
+    pub fn coerce(self, target: ValueType) -> Result<Value, CoercionError> {
+        let from = self.value_type();
+        let fail = |reason: &str| CoercionError {
+            from,
+            to: target,
+            reason: reason.to_string(),
+        };
+
+        if target == ValueType::Any || target == from {
+            return Ok(self);
+        }
+
+        match (self, target) {
+            (Value::Bool(b), ValueType::String) => Ok(Value::String(b.to_string())),
+            (Value::Number(n), ValueType::String) => Ok(Value::String(n.to_string())),
+            (Value::Bytes(b), ValueType::String) => String::from_utf8(b)
+                .map(Value::String)
+                .map_err(|_| fail("bytes are not valid UTF-8")),
+            (value @ (Value::Array(_) | Value::Object(_)), ValueType::String) => {
+                Ok(Value::String(serde_json::Value::from(value).to_string()))
+            }
+
+            (Value::Bool(b), ValueType::Number) => Ok(Value::from(b as i64)),
+            (Value::String(s), ValueType::Number) => {
+                let trimmed = s.trim();
+                trimmed
+                    .parse::<i64>()
+                    .map(Value::from)
+                    .ok()
+                    .or_else(|| {
+                        // `NaN` and `inf` parse, but have no JSON representation.
+                        trimmed
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|n| n.is_finite())
+                            .map(Value::from)
+                    })
+                    .ok_or_else(|| fail("string is not a number"))
+            }
+
+            (Value::Null, ValueType::Bool) => Ok(Value::Bool(false)),
+            (Value::Number(n), ValueType::Bool) => Ok(Value::Bool(n.as_f64() != Some(0.0))),
+            (Value::String(s), ValueType::Bool) => match s.trim() {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                _ => Err(fail("string is not `true` or `false`")),
+            },
+
+            (Value::String(s), ValueType::Bytes) => Ok(Value::Bytes(s.into_bytes())),
+            (Value::Array(items), ValueType::Bytes) => items
+                .iter()
+                .map(|item| item.as_u64().and_then(|n| u8::try_from(n).ok()))
+                .collect::<Option<Vec<u8>>>()
+                .map(Value::Bytes)
+                .ok_or_else(|| fail("array contains values outside 0..=255")),
+
+            (Value::Bytes(b), ValueType::Array) => Ok(Value::Array(
+                b.into_iter().map(|byte| Value::from(byte as i64)).collect(),
+            )),
+
+            _ => Err(fail("no conversion exists")),
+        }
+    }
+}
+
+impl ValueType {
+    /// Describes whether values of this type can be passed where `target` is expected.
+    pub fn coercion_to(self, target: ValueType) -> Coercion {
+        use ValueType::*;
+
+        if self == target || target == Any {
+            return Coercion::Exact;
+        }
+
+        match (self, target) {
+            // The type is only known at runtime.
+            (Any, _) => Coercion::Fallible,
+            (Bool | Number | Array | Object, String) => Coercion::Lossless,
+            (Bool, Number) | (Null | Number, Bool) => Coercion::Lossless,
+            (String, Bytes) | (Bytes, Array) => Coercion::Lossless,
+            (Bytes, String) | (String, Number | Bool) | (Array, Bytes) => Coercion::Fallible,
+            _ => Coercion::Impossible,
+        }
+    }
+
+    /// Maps a JSON schema, such as the ones plugins generate for their
+    /// arguments, onto the closest value type.
+    pub fn from_json_schema(schema: &serde_json::Value) -> ValueType {
+        let from_name = |name: &str| match name {
+            "null" => Some(ValueType::Null),
+            "boolean" => Some(ValueType::Bool),
+            "number" | "integer" => Some(ValueType::Number),
+            "string" => Some(ValueType::String),
+            "array" => Some(ValueType::Array),
+            "object" => Some(ValueType::Object),
+            _ => None,
+        };
+
+        match schema.get("type") {
+            Some(serde_json::Value::String(name)) => from_name(name).unwrap_or_default(),
+            // `Option<T>` is generated as `["T", "null"]`.
+            Some(serde_json::Value::Array(names)) => names
+                .iter()
+                .filter_map(|name| name.as_str())
+                .filter(|name| *name != "null")
+                .find_map(from_name)
+                .unwrap_or_default(),
+            _ if schema.get("$ref").is_some() || schema.get("properties").is_some() => {
+                ValueType::Object
+            }
+            _ => ValueType::Any,
+        }
+    }
+}
+
+impl Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ValueType::Null => "null",
+            ValueType::Bool => "bool",
+            ValueType::Number => "number",
+            ValueType::String => "string",
+            ValueType::Bytes => "bytes",
+            ValueType::Array => "array",
+            ValueType::Object => "object",
+            ValueType::Any => "any",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl Display for CoercionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "cannot convert {} into {}: {}",
+            self.from, self.to, self.reason
+        )
+    }
+}
+
+impl std::error::Error for CoercionError {}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::Value::from(self.clone()))
+    }
+}
+
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) => Value::Number(n),
+            serde_json::Value::String(s) => Value::String(s),
+            serde_json::Value::Array(a) => Value::Array(a.into_iter().map(Value::from).collect()),
+            serde_json::Value::Object(o) => {
+                if let (1, Some(serde_json::Value::String(encoded))) =
+                    (o.len(), o.get(BYTES_MARKER))
+                    && let Ok(bytes) = BASE64.decode(encoded)
+                {
+                    return Value::Bytes(bytes);
+                }
+
+                let single = o.len() == 1;
+                Value::Object(
+                    o.into_iter()
+                        .map(|(k, v)| {
+                            let k = single
+                                .then(|| escape_marker_key(&k, false))
+                                .flatten()
+                                .unwrap_or(k);
+                            (k, Value::from(v))
+                        })
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+impl From<Value> for serde_json::Value {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(b) => serde_json::Value::Bool(b),
+            Value::Number(n) => serde_json::Value::Number(n),
+            Value::String(s) => serde_json::Value::String(s),
+            Value::Bytes(b) => serde_json::json!({ BYTES_MARKER: BASE64.encode(b) }),
+            Value::Array(a) => {
+                serde_json::Value::Array(a.into_iter().map(serde_json::Value::from).collect())
+            }
+            Value::Object(o) => {
+                let single = o.len() == 1;
+                serde_json::Value::Object(
+                    o.into_iter()
+                        .map(|(k, v)| {
+                            let k = single
+                                .then(|| escape_marker_key(&k, true))
+                                .flatten()
+                                .unwrap_or(k);
+                            (k, serde_json::Value::from(v))
+                        })
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Number(value.into())
+    }
+}
+
+impl From<f64> for Value {
+    /// Non-finite numbers can't be represented and become [`Value::Null`].
+    fn from(value: f64) -> Self {
+        serde_json::Number::from_f64(value)
+            .map(Value::Number)
+            .unwrap_or_default()
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Value::Bytes(value)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Self {
+        Value::Array(value)
+    }
+}
+
+impl From<BTreeMap<String, Value>> for Value {
+    fn from(value: BTreeMap<String, Value>) -> Self {
+        Value::Object(value)
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Number(n) => n.serialize(serializer),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Bytes(b) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(BYTES_MARKER, &BASE64.encode(b))?;
+                map.end()
+            }
+            Value::Array(a) => a.serialize(serializer),
+            Value::Object(o) => match o.iter().next() {
+                Some((key, value)) if o.len() == 1 => match escape_marker_key(key, true) {
+                    Some(escaped) => {
+                        let mut map = serializer.serialize_map(Some(1))?;
+                        map.serialize_entry(&escaped, value)?;
+                        map.end()
+                    }
+                    None => o.serialize(serializer),
+                },
+                _ => o.serialize(serializer),
+            },
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        serde_json::Value::deserialize(deserializer).map(Value::from)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::value::{Coercion, Value, ValueType};
+
+    #[test]
+    fn value_should_round_trip_through_json() {
+        let original = json!({ "url": "https://example.com", "retries": 3, "tags": ["a", null] });
+        let value = Value::from(original.clone());
+
+        assert_eq!(serde_json::to_value(&value).unwrap(), original);
+    }
+
+    #[test]
+    fn bytes_should_round_trip_through_json() {
+        let value = Value::Bytes(vec![0, 1, 254, 255]);
+        let json = serde_json::to_value(&value).unwrap();
+
+        assert_eq!(serde_json::from_value::<Value>(json).unwrap(), value);
+    }
+
+    #[test]
+    fn objects_shaped_like_bytes_should_round_trip_through_json() {
+        for key in ["$bytes", "$$bytes"] {
+            let value = Value::Object([(key.to_string(), Value::from("AAE="))].into());
+            let json = serde_json::to_value(&value).unwrap();
+
+            assert_eq!(
+                serde_json::from_value::<Value>(json.clone()).unwrap(),
+                value
+            );
+            assert_eq!(Value::from(json), value);
+        }
+    }
+
+    #[test]
+    fn coerce_should_follow_coercion_rules() {
+        assert_eq!(
+            Value::from("42").coerce(ValueType::Number),
+            Ok(Value::from(42i64))
+        );
+        assert_eq!(
+            Value::from(1.5).coerce(ValueType::String),
+            Ok(Value::from("1.5"))
+        );
+        assert!(Value::from("abc").coerce(ValueType::Number).is_err());
+        assert!(Value::from("NaN").coerce(ValueType::Number).is_err());
+        assert!(Value::from("inf").coerce(ValueType::Number).is_err());
+        assert!(Value::from(true).coerce(ValueType::Object).is_err());
+    }
+
+    #[test]
+    fn coercion_to_should_classify_type_pairs() {
+        assert_eq!(
+            ValueType::Number.coercion_to(ValueType::Number),
+            Coercion::Exact
+        );
+        assert_eq!(
+            ValueType::Number.coercion_to(ValueType::String),
+            Coercion::Lossless
+        );
+        assert_eq!(
+            ValueType::String.coercion_to(ValueType::Number),
+            Coercion::Fallible
+        );
+        assert_eq!(
+            ValueType::Object.coercion_to(ValueType::Array),
+            Coercion::Impossible
+        );
+    }
+}
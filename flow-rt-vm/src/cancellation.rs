@@ -0,0 +1,65 @@
+use std::{
+    pin::pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use tokio::sync::Notify;
+
+/// Lets a run be cancelled from the outside.
+///
+/// Cancelling is cooperative: every node still waiting to run, or running,
+/// stops at its next await point and reports itself as cancelled. Function
+/// graphs called by the run are cancelled along with it.
+#[derive(Default)]
+pub struct Cancellation {
+    cancelled: AtomicBool,
+    changed: Notify,
+}
+
+impl Cancellation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.changed.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Cancels `self` once the returned guard is dropped, such as along with
+    /// the future of a node that stops waiting for work it handed off.
+    pub fn cancel_on_drop(self: &Arc<Self>) -> CancelOnDrop {
+        CancelOnDrop(self.clone())
+    }
+
+    /// Completes once the run is cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            let mut changed = pin!(self.changed.notified());
+            // Registered before checking, so a cancel in between isn't missed.
+            changed.as_mut().enable();
+
+            if self.is_cancelled() {
+                return;
+            }
+
+            changed.await;
+        }
+    }
+}
+
+/// Cancels its [`Cancellation`] when dropped, see [`Cancellation::cancel_on_drop`].
+pub struct CancelOnDrop(Arc<Cancellation>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
@@ -0,0 +1,256 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+};
+
+use flow_rt_shared::{schemas::FlowGraph, value::Value};
+use flow_rt_vm::{
+    error::RuntimeError,
+    events::{EventBus, EventRun, EventRunObserver},
+    interpreter::{Interpreter, RunOptions},
+    memo::{DEFAULT_CACHE_BYTES, DiskCache, project_cache_directory},
+    script::ScriptEngine,
+    typecheck::schema_ports,
+};
+use serde::Serialize;
+
+use crate::cli::{
+    plugins::PluginHost,
+    project::{Project, ProjectInformation},
+};
+
+mod plugins;
+mod project;
+
+/// Why a command failed. Decides the exit code of the process.
+pub(crate) enum CliError {
+    /// The project, its plugins, the graph or the input could not be loaded.
+    Setup(String),
+    /// The graph was started but failed.
+    Run(RuntimeError),
+}
+
+impl CliError {
+    pub(crate) fn exit_code(&self) -> ExitCode {
+        match self {
+            CliError::Run(_) => ExitCode::from(1),
+            // 2 is taken by clap for invalid arguments.
+            CliError::Setup(_) => ExitCode::from(3),
+        }
+    }
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Setup(message) => write!(f, "{message}"),
+            CliError::Run(error) => write!(f, "run failed: {error}"),
+        }
+    }
+}
+
+pub(crate) struct RunCommand {
+    pub project: PathBuf,
+    pub graph: String,
+    pub input: Option<String>,
+    pub modules: Option<PathBuf>,
+    pub max_concurrency: Option<usize>,
+    pub timeout_ms: Option<u64>,
+    pub no_cache: bool,
+    pub environment: Option<String>,
+}
+
+/// Runs `command.graph` of the project with the plugins of the modules
+/// directory and prints its outputs as JSON.
+///
+/// Every other graph of the project is compiled as well, so the graph can
+/// call function graphs and fire events, but only the graph that is run has
+/// to compile.
+pub(crate) async fn run(command: RunCommand) -> Result<(), CliError> {
+    let project = Project::open(&command.project).map_err(CliError::Setup)?;
+
+    let input = match &command.input {
+        Some(input) => serde_json::from_str::<Value>(input)
+            .map_err(|e| CliError::Setup(format!("--input is not valid JSON: {e}")))?,
+        None => Value::Null,
+    };
+
+    let modules = command
+        .modules
+        .clone()
+        .unwrap_or_else(|| project.location.join("modules"));
+    let host = Arc::new(PluginHost::load(&modules).map_err(CliError::Setup)?);
+
+    let mut compiled = project.compile(host.as_ref());
+    let program = compiled
+        .remove(&command.graph)
+        .ok_or_else(|| {
+            CliError::Setup(format!(
+                "Graph {} does not exist in {:?}.",
+                command.graph, project.location
+            ))
+        })?
+        .map_err(|e| CliError::Setup(format!("Graph {} failed to compile: {e}", command.graph)))?;
+
+    let mut graphs = compiled
+        .into_iter()
+        .filter_map(|(name, program)| Some((name, program.ok()?)))
+        .collect::<BTreeMap<_, _>>();
+    graphs.insert(command.graph.clone(), program.clone());
+    let graphs = Arc::new(graphs);
+
+    let environment = Arc::new(
+        project
+            .environments
+            .load(&project.location, command.environment.as_deref())
+            .map_err(CliError::Setup)?,
+    );
+
+    let scripts = Arc::new(ScriptEngine::new(project.scripts.clone()));
+    let bus = EventBus::new(host.clone());
+    bus.set_functions(graphs.clone());
+    bus.set_observer(Arc::new(EventWarnings));
+    bus.set_scripts(scripts.clone());
+    bus.set_environment(environment.clone());
+    for (name, program) in graphs.iter() {
+        if program.events().next().is_some() {
+            bus.register(name.clone(), program.clone());
+        }
+    }
+
+    let mut interpreter = Interpreter::new(program, host)
+        .with_graphs(graphs)
+        .with_scripts(scripts)
+        .with_environment(environment);
+    if !command.no_cache {
+        // Without a cache the graph still runs; pure functions are just called every time.
+        match DiskCache::open(
+            project_cache_directory(&project.location),
+            DEFAULT_CACHE_BYTES,
+        ) {
+            Ok(cache) => interpreter = interpreter.with_cache(Arc::new(cache)),
+            Err(e) => eprintln!("warning: the output cache is unavailable: {e}"),
+        }
+    }
+
+    let output = interpreter
+        .with_events(bus)
+        .with_options(RunOptions {
+            max_concurrency: command.max_concurrency,
+            node_timeout_ms: command.timeout_ms,
+            ..Default::default()
+        })
+        .run(input)
+        .await
+        .map_err(CliError::Run)?;
+
+    print_json(&output)
+}
+
+/// Warns about event graphs started by `eventTrigger` nodes that fail, as
+/// their outcome isn't part of the output.
+struct EventWarnings;
+
+impl EventRunObserver for EventWarnings {
+    fn finished(&self, event: &str, run: &EventRun) {
+        if let Err(e) = &run.result {
+            eprintln!("warning: event graph {} failed on {event}: {e}", run.graph);
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ProjectSummary {
+    project: ProjectInformation,
+    graphs: Vec<GraphSummary>,
+}
+
+/// A graph as listed by the `list` command.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphSummary {
+    name: String,
+    /// How the graph can be started: `start`, `function` or `event`.
+    entries: Vec<&'static str>,
+    /// The events the graph listens to.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    triggered_by: Vec<String>,
+    /// The arguments declared by the `fnEntry` nodes of a function graph.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    inputs: BTreeMap<String, serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Prints every graph of the project with how it is started and its input schema.
+pub(crate) fn list(project: &Path) -> Result<(), CliError> {
+    let project = Project::open(project).map_err(CliError::Setup)?;
+
+    let graphs = project
+        .graphs
+        .iter()
+        .map(|(name, graph)| match graph {
+            Ok(graph) => summarize(name, graph),
+            Err(error) => GraphSummary {
+                name: name.clone(),
+                entries: vec![],
+                triggered_by: vec![],
+                inputs: BTreeMap::new(),
+                error: Some(error.clone()),
+            },
+        })
+        .collect::<Vec<_>>();
+
+    print_json(&ProjectSummary {
+        project: project.info,
+        graphs,
+    })
+}
+
+fn summarize(name: &str, graph: &FlowGraph) -> GraphSummary {
+    let mut summary = GraphSummary {
+        name: name.to_string(),
+        entries: vec![],
+        triggered_by: vec![],
+        inputs: BTreeMap::new(),
+        error: None,
+    };
+
+    for node in graph.nodes.as_deref().unwrap_or_default() {
+        let entry = match node.node_type.as_str() {
+            "startNode" => "start",
+            "fnEntry" => {
+                for port in schema_ports(node) {
+                    summary.inputs.insert(
+                        port.id,
+                        serde_json::to_value(port.value_type).unwrap_or_default(),
+                    );
+                }
+                "function"
+            }
+            "eventListener" => {
+                if let Some(event) = node.data.get("triggeredBy").and_then(|e| e.as_str()) {
+                    summary.triggered_by.push(event.to_string());
+                }
+                "event"
+            }
+            _ => continue,
+        };
+
+        if !summary.entries.contains(&entry) {
+            summary.entries.push(entry);
+        }
+    }
+
+    summary
+}
+
+fn print_json(value: &impl Serialize) -> Result<(), CliError> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| CliError::Setup(format!("Failed to serialize the result: {e}")))?;
+    println!("{json}");
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use dyn_rt::{
+    attach::AttachedPlugin,
+    registry::{PluginRegistry, PluginRegistryBuilder},
+};
+use flow_rt_shared::value::Value;
+use flow_rt_vm::{
+    compiler::FunctionCatalog,
+    interpreter::{BoxFuture, FunctionHost},
+    ir::FunctionRef,
+    memo::marked_pure,
+    typecheck::FunctionSignature,
+};
+
+/// The plugins loaded from a modules directory, exposed to the compiler and
+/// interpreter the same way the app exposes its plugin registry.
+pub(crate) struct PluginHost {
+    registry: Mutex<PluginRegistry>,
+}
+
+impl PluginHost {
+    /// Loads every plugin library (`.so`, `.dll` or `.dylib`) in `directory`.
+    pub(crate) fn load(directory: &Path) -> Result<Self, String> {
+        let entries = std::fs::read_dir(directory)
+            .map_err(|e| format!("Failed to read modules directory {directory:?}: {e}"))?;
+
+        let libraries = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let is_library = path
+                    .extension()
+                    .is_some_and(|ext| ext == "so" || ext == "dll" || ext == "dylib");
+                (path.is_file() && is_library).then_some(path)
+            })
+            .collect::<Vec<PathBuf>>();
+
+        let registry = PluginRegistryBuilder::new()
+            .add_libraries(libraries)
+            .build();
+
+        Ok(Self {
+            registry: Mutex::new(registry),
+        })
+    }
+
+    fn find_plugin(&self, name: &str) -> Result<Arc<AttachedPlugin>, String> {
+        self.registry
+            .lock()
+            .map_err(|e| format!("Could not lock registry context: {e}"))?
+            .get_plugins_map()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("Plugin {name} is not loaded."))
+    }
+
+    fn descriptor(&self, plugin: &str, function: &str) -> Option<serde_json::Value> {
+        let plugin = self.find_plugin(plugin).ok()?;
+        let descriptor = plugin
+            .functions
+            .iter()
+            .find(|(name, _)| *name == function)
+            .map(|(_, descriptor)| descriptor)?;
+
+        serde_json::to_value(descriptor).ok()
+    }
+}
+
+impl FunctionCatalog for PluginHost {
+    fn contains(&self, plugin: &str, function: &str) -> bool {
+        self.find_plugin(plugin)
+            .map(|p| p.functions.iter().any(|(name, _)| name == function))
+            .unwrap_or(false)
+    }
+
+    fn signature(&self, plugin: &str, function: &str) -> Option<FunctionSignature> {
+        let descriptor = self.descriptor(plugin, function)?;
+        Some(FunctionSignature::from_json_schema(
+            descriptor.get("schema")?,
+            descriptor.get("returns"),
+        ))
+    }
+
+    fn is_pure(&self, plugin: &str, function: &str) -> bool {
+        self.descriptor(plugin, function)
+            .is_some_and(|descriptor| marked_pure(&descriptor))
+    }
+}
+
+impl FunctionHost for PluginHost {
+    fn call<'a>(
+        &'a self,
+        function: &'a FunctionRef,
+        arguments: Value,
+    ) -> BoxFuture<'a, Result<Value, String>> {
+        Box::pin(async move {
+            let plugin = self.find_plugin(&function.plugin)?;
+
+            plugin
+                .invoke(&function.function, serde_json::Value::from(arguments))
+                .await
+                .map(Value::from)
+                .map_err(|e| format!("{}::{} failed: {e}", function.plugin, function.function))
+        })
+    }
+
+    fn plugin_hash(&self, plugin: &str) -> Option<String> {
+        self.find_plugin(plugin)
+            .ok()
+            .map(|plugin| plugin.blake3_hash.clone())
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use flow_rt_shared::schemas::FlowGraph;
+use flow_rt_vm::{
+    compiler::{FunctionCatalog, compile_with_scripts},
+    environment::Environments,
+    ir::Program,
+    script::{ScriptDirectory, ScriptSandbox},
+};
+use serde::{Deserialize, Serialize};
+
+/// The `[info]` table of `Flow.toml`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ProjectInformation {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub version: String,
+}
+
+#[derive(Deserialize)]
+struct ProjectTOMLConfiguration {
+    #[serde(rename = "info", alias = "information", alias = "workspace")]
+    info: ProjectInformation,
+    #[serde(default)]
+    scripts: ScriptSandbox,
+    #[serde(default)]
+    environments: Environments,
+}
+
+/// A project directory as laid out by the editor: `Flow.toml` at the root
+/// and one `.jfg` file per graph in `flows`.
+pub(crate) struct Project {
+    pub location: PathBuf,
+    pub info: ProjectInformation,
+    /// What the project's scripts may do, from the `[scripts]` table.
+    pub scripts: ScriptSandbox,
+    /// The named environments of the project, from the `[environments]` table.
+    pub environments: Environments,
+    /// Every graph of the project by name, or the reason it couldn't be read.
+    pub graphs: BTreeMap<String, Result<FlowGraph, String>>,
+}
+
+impl Project {
+    pub(crate) fn open(location: &Path) -> Result<Self, String> {
+        let toml_location = location.join("Flow.toml");
+        let toml = std::fs::read_to_string(&toml_location)
+            .map_err(|e| format!("{toml_location:?} could not be read: {e}"))?;
+        let configuration = toml::from_str::<ProjectTOMLConfiguration>(&toml)
+            .map_err(|e| format!("{toml_location:?} is not a valid project file: {e}"))?;
+
+        let graphs = std::fs::read_dir(location.join("flows"))
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "jfg"))
+                    .filter_map(|path| {
+                        let name = path.file_stem()?.to_str()?.to_string();
+                        Some((name, read_graph(&path)))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            location: location.to_path_buf(),
+            info: configuration.info,
+            scripts: configuration.scripts,
+            environments: configuration.environments,
+            graphs,
+        })
+    }
+
+    /// Compiles every graph that could be read, keeping the compile error of
+    /// the others.
+    pub(crate) fn compile(
+        &self,
+        catalog: &dyn FunctionCatalog,
+    ) -> BTreeMap<String, Result<Arc<Program>, String>> {
+        let scripts = ScriptDirectory::of_project(&self.location);
+
+        self.graphs
+            .iter()
+            .map(|(name, graph)| {
+                let program = graph.as_ref().map_err(Clone::clone).and_then(|graph| {
+                    compile_with_scripts(graph, catalog, &scripts)
+                        .map(Arc::new)
+                        .map_err(|e| e.to_string())
+                });
+                (name.clone(), program)
+            })
+            .collect()
+    }
+}
+
+fn read_graph(location: &Path) -> Result<FlowGraph, String> {
+    let json = std::fs::read_to_string(location)
+        .map_err(|e| format!("{location:?} could not be read: {e}"))?;
+    serde_json::from_str(&json).map_err(|e| format!("{location:?} is not a valid graph: {e}"))
+}
@@ -0,0 +1,886 @@
+use std::collections::BTreeMap;
+
+use flow_rt_shared::{
+    schemas::{FlowGraph, FlowGraphEdge, FlowGraphNode, FlowGraphPort},
+    value::{Value, ValueType},
+};
+
+use crate::{
+    error::{CompileError, CompileErrorKind},
+    ir::{
+        Binding, ConstantId, DEFAULT_PORT, ELSE_PORT, EntryKind, FunctionRef, GateKind,
+        Instruction, IterationKind, Op, Program, Script, ScriptId, SwitchCase, THEN_PORT, Variable,
+        VariableId, node_types,
+    },
+    retry::RetryPolicy,
+    scheduler::schedule_flow_graph,
+    script::{ScriptEngine, ScriptLoader},
+    typecheck::{FunctionSignature, schema_ports},
+};
+
+/// Source of the plugin functions a graph is allowed to call.
+pub trait FunctionCatalog {
+    fn contains(&self, plugin: &str, function: &str) -> bool;
+
+    /// The typed signature of a function, used by [`crate::typecheck`].
+    /// Functions without one are treated as untyped.
+    fn signature(&self, _plugin: &str, _function: &str) -> Option<FunctionSignature> {
+        None
+    }
+
+    /// Whether the function always produces the same output for the same
+    /// arguments, without side effects, so its outputs may be cached.
+    fn is_pure(&self, _plugin: &str, _function: &str) -> bool {
+        false
+    }
+
+    /// The arguments of the function graph `graph`, used by
+    /// [`crate::typecheck`] to check the `fnTrigger` nodes calling it.
+    /// Graphs without one are treated as untyped.
+    fn graph_schema(&self, _graph: &str) -> Option<Vec<FlowGraphPort>> {
+        None
+    }
+}
+
+/// Lowers `graph` into a [`Program`], resolving every `foreignFunctionNode`
+/// against `catalog`. Graphs with `scriptNode`s need [`compile_with_scripts`].
+pub fn compile(graph: &FlowGraph, catalog: &dyn FunctionCatalog) -> Result<Program, CompileError> {
+    compile_with_scripts(graph, catalog, &BTreeMap::new())
+}
+
+/// Like [`compile`], also resolving every `scriptNode` against `scripts`.
+/// Scripts are compiled along with the graph, so syntax errors surface here.
+pub fn compile_with_scripts(
+    graph: &FlowGraph,
+    catalog: &dyn FunctionCatalog,
+    scripts: &dyn ScriptLoader,
+) -> Result<Program, CompileError> {
+    let nodes = graph.nodes.as_deref().unwrap_or_default();
+
+    if nodes.is_empty() {
+        return Err(CompileError::new(CompileErrorKind::EmptyGraph));
+    }
+
+    let (dependencies, schedule) = schedule_flow_graph(graph)?;
+
+    // Maps node index -> instruction index, so edges can be rewritten after reordering.
+    let mut position = vec![0; nodes.len()];
+    for (instruction_id, node_index) in schedule.order.iter().enumerate() {
+        position[*node_index] = instruction_id;
+    }
+
+    let mut program = Program {
+        variables: variables(graph)?,
+        ..Default::default()
+    };
+    for node_index in &schedule.order {
+        let node = &nodes[*node_index];
+        let op = lower_node(node, catalog, scripts, &mut program)?;
+
+        if matches!(op, Op::Entry(_)) {
+            program.entry_points.push(program.instructions.len());
+        }
+
+        let mut input_ports = node.input_ports();
+        if matches!(op, Op::Entry(EntryKind::Function)) && input_ports.is_empty() {
+            // A function graph checks the arguments it is called with against its schema.
+            input_ports = schema_ports(node);
+        }
+
+        program.instructions.push(Instruction {
+            node_id: node.id.clone(),
+            op,
+            inputs: vec![],
+            outputs: vec![],
+            input_ports,
+            timeout_ms: node.data.get("timeoutMs").and_then(|t| t.as_u64()),
+            retry: retry_policy(node)?,
+        });
+    }
+
+    let index_by_id = dependencies.index_by_id();
+    for edge in graph.edges.iter().flatten() {
+        let source_index = index_by_id.get(edge.source.as_str()).copied();
+        let target_index = index_by_id.get(edge.target.as_str()).copied();
+        let (Some(source_index), Some(target_index)) = (source_index, target_index) else {
+            continue;
+        };
+
+        let (source_op, source_node) = (
+            &program.instructions[position[source_index]].op,
+            &nodes[source_index],
+        );
+        let source_ports = match branch_ports(source_op) {
+            Some(_) if edge.source_handle.is_none() => {
+                return Err(CompileError::at(
+                    &source_node.id,
+                    CompileErrorKind::MissingBranch {
+                        edge_id: edge.id.clone(),
+                    },
+                ));
+            }
+            Some(ports) => ports,
+            None => source_node.output_ports(),
+        };
+        let source_port = resolve_port(
+            source_node,
+            &source_ports,
+            edge,
+            edge.source_handle.as_ref(),
+        )?;
+        let target_port = resolve_port(
+            &nodes[target_index],
+            &nodes[target_index].input_ports(),
+            edge,
+            edge.target_handle.as_ref(),
+        )?;
+
+        // Only nodes with several outputs produce an object that has to be split per port,
+        // branch nodes pass their value on under the port they take.
+        let source_port = source_port.filter(|_| source_ports.len() > 1 || source_op.is_branch());
+
+        let (source, target) = (position[source_index], position[target_index]);
+        program.instructions[source].outputs.push(target);
+        program.instructions[target].inputs.push(Binding {
+            edge_id: edge.id.clone(),
+            source,
+            source_port,
+            target_port,
+        });
+    }
+
+    program.unreachable = schedule
+        .unreachable
+        .iter()
+        .map(|index| nodes[*index].id.clone())
+        .collect();
+
+    Ok(program)
+}
+
+/// Checks that `handle` names one of the declared `ports` of `node`.
+///
+/// Nodes that don't declare any ports accept every handle, so graphs saved
+/// before ports existed keep working.
+fn resolve_port(
+    node: &FlowGraphNode,
+    ports: &[FlowGraphPort],
+    edge: &FlowGraphEdge,
+    handle: Option<&String>,
+) -> Result<Option<String>, CompileError> {
+    let Some(handle) = handle else {
+        return Ok(None);
+    };
+
+    if !ports.is_empty() && !ports.iter().any(|port| port.id == *handle) {
+        return Err(CompileError::at(
+            &node.id,
+            CompileErrorKind::UnknownPort {
+                edge_id: edge.id.clone(),
+                port: handle.clone(),
+            },
+        ));
+    }
+
+    Ok(Some(handle.clone()))
+}
+
+fn lower_node(
+    node: &FlowGraphNode,
+    catalog: &dyn FunctionCatalog,
+    scripts: &dyn ScriptLoader,
+    program: &mut Program,
+) -> Result<Op, CompileError> {
+    match node.node_type.as_str() {
+        node_types::START_NODE => Ok(Op::Entry(EntryKind::Start)),
+        node_types::FN_ENTRY => Ok(Op::Entry(EntryKind::Function)),
+        node_types::EVENT_LISTENER => Ok(Op::Entry(EntryKind::Event {
+            name: required_str(node, "triggeredBy")?.to_string(),
+        })),
+        node_types::EVENT_TRIGGER => Ok(Op::Emit {
+            event: required_str(node, "eventName")?.to_string(),
+        }),
+        node_types::FOREIGN_FUNCTION_NODE => {
+            let plugin = required_str(node, "pluginName")?;
+            let function = required_str(node, "functionName")?;
+
+            if !catalog.contains(plugin, function) {
+                return Err(CompileError::at(
+                    &node.id,
+                    CompileErrorKind::UnknownFunction {
+                        plugin: plugin.to_string(),
+                        function: function.to_string(),
+                    },
+                ));
+            }
+
+            let pure = catalog.is_pure(plugin, function);
+            let function = intern_function(
+                program,
+                FunctionRef {
+                    plugin: plugin.to_string(),
+                    function: function.to_string(),
+                },
+            );
+
+            Ok(Op::Call {
+                function,
+                arguments: constant_arguments(node, program),
+                pure,
+            })
+        }
+        node_types::FN_TRIGGER => Ok(Op::CallGraph {
+            graph: required_str(node, "graphName")?.to_string(),
+            arguments: constant_arguments(node, program),
+        }),
+        node_types::SCRIPT_NODE => {
+            let name = required_str(node, "scriptName")?;
+            let source = scripts.load(name).map_err(|message| {
+                CompileError::at(
+                    &node.id,
+                    CompileErrorKind::UnknownScript {
+                        script: name.to_string(),
+                        message,
+                    },
+                )
+            })?;
+
+            if let Err(error) = ScriptEngine::shared().compile(&source) {
+                return Err(CompileError::at(
+                    &node.id,
+                    CompileErrorKind::InvalidScript {
+                        script: name.to_string(),
+                        error,
+                    },
+                ));
+            }
+
+            let script = intern_script(
+                program,
+                Script {
+                    name: name.to_string(),
+                    source,
+                },
+            );
+
+            Ok(Op::Script {
+                script,
+                arguments: constant_arguments(node, program),
+            })
+        }
+        node_types::IF_NODE => Ok(Op::If),
+        node_types::SWITCH_NODE => Ok(Op::Switch {
+            cases: switch_cases(node)?,
+        }),
+        node_types::AND_GATE => Ok(Op::Gate(GateKind::And)),
+        node_types::OR_GATE => Ok(Op::Gate(GateKind::Or)),
+        node_types::NOT_GATE => Ok(Op::Gate(GateKind::Not)),
+        node_types::GET_VARIABLE => Ok(Op::GetVariable {
+            variable: variable_of(node, program)?,
+        }),
+        node_types::SET_VARIABLE => Ok(Op::SetVariable {
+            variable: variable_of(node, program)?,
+        }),
+        node_types::MAP_NODE => iteration(node, IterationKind::Map, program),
+        node_types::FILTER_NODE => iteration(node, IterationKind::Filter, program),
+        node_types::REDUCE_NODE => iteration(node, IterationKind::Reduce, program),
+        other => Err(CompileError::at(
+            &node.id,
+            CompileErrorKind::UnknownNodeType {
+                node_type: other.to_string(),
+            },
+        )),
+    }
+}
+
+/// The output ports of a branching `op`, every edge leaving it has to name one of them.
+fn branch_ports(op: &Op) -> Option<Vec<FlowGraphPort>> {
+    let ports = match op {
+        Op::If => vec![THEN_PORT.to_string(), ELSE_PORT.to_string()],
+        Op::Switch { cases } => cases
+            .iter()
+            .map(|case| case.port.clone())
+            .chain([DEFAULT_PORT.to_string()])
+            .collect(),
+        _ => return None,
+    };
+
+    Some(
+        ports
+            .into_iter()
+            .map(|id| FlowGraphPort {
+                id,
+                value_type: ValueType::Any,
+                required: false,
+            })
+            .collect(),
+    )
+}
+
+/// The `cases` of a `switchNode`, each one taking the port named after its
+/// value: strings as they are, other values as JSON.
+fn switch_cases(node: &FlowGraphNode) -> Result<Vec<SwitchCase>, CompileError> {
+    let invalid = |message: String| {
+        CompileError::at(
+            &node.id,
+            CompileErrorKind::InvalidField {
+                field: "cases".to_string(),
+                message,
+            },
+        )
+    };
+
+    let values = match node.data.get("cases") {
+        None => vec![],
+        Some(serde_json::Value::Array(values)) => values.clone(),
+        Some(_) => return Err(invalid("expected an array of values".to_string())),
+    };
+
+    let mut cases: Vec<SwitchCase> = vec![];
+    for value in values {
+        let port = match &value {
+            serde_json::Value::String(port) => port.clone(),
+            other => other.to_string(),
+        };
+        if port == DEFAULT_PORT {
+            return Err(invalid(format!(
+                "`{DEFAULT_PORT}` is the port taken when no case matches"
+            )));
+        }
+        if cases.iter().any(|case| case.port == port) {
+            return Err(invalid(format!("case `{port}` is listed twice")));
+        }
+        cases.push(SwitchCase {
+            port,
+            value: Value::from(value),
+        });
+    }
+
+    Ok(cases)
+}
+
+/// The variables declared on `graph`, with their defaults coerced to their types.
+fn variables(graph: &FlowGraph) -> Result<Vec<Variable>, CompileError> {
+    let mut variables: Vec<Variable> = vec![];
+
+    for declared in graph.variables.iter().flatten() {
+        let invalid = |message: String| {
+            CompileError::new(CompileErrorKind::InvalidVariable {
+                variable: declared.name.clone(),
+                message,
+            })
+        };
+
+        if declared.name.is_empty() {
+            return Err(invalid("variables need a name".to_string()));
+        }
+        if variables.iter().any(|v| v.name == declared.name) {
+            return Err(invalid("declared twice".to_string()));
+        }
+
+        // Variables without a default start out as `null`, whatever their type.
+        let default = match Value::from(declared.default.clone()) {
+            Value::Null => Value::Null,
+            default => default
+                .coerce(declared.value_type)
+                .map_err(|e| invalid(format!("default doesn't fit its type: {e}")))?,
+        };
+
+        variables.push(Variable {
+            name: declared.name.clone(),
+            value_type: declared.value_type,
+            default,
+        });
+    }
+
+    Ok(variables)
+}
+
+/// The variable named by `variableName`, which the graph has to declare.
+fn variable_of(node: &FlowGraphNode, program: &Program) -> Result<VariableId, CompileError> {
+    let name = required_str(node, "variableName")?;
+
+    program
+        .variables
+        .iter()
+        .position(|variable| variable.name == name)
+        .ok_or_else(|| {
+            CompileError::at(
+                &node.id,
+                CompileErrorKind::UnknownVariable {
+                    variable: name.to_string(),
+                },
+            )
+        })
+}
+
+/// A `mapNode`, `filterNode` or `reduceNode`, running the function graph
+/// named by `graphName` once per item.
+fn iteration(
+    node: &FlowGraphNode,
+    kind: IterationKind,
+    program: &mut Program,
+) -> Result<Op, CompileError> {
+    let invalid = |field: &str, message: String| {
+        CompileError::at(
+            &node.id,
+            CompileErrorKind::InvalidField {
+                field: field.to_string(),
+                message,
+            },
+        )
+    };
+
+    let graph = required_str(node, "graphName")?.to_string();
+
+    let parallelism = match node.data.get("parallelism") {
+        None | Some(serde_json::Value::Null) => None,
+        Some(parallelism) => Some(
+            parallelism
+                .as_u64()
+                .and_then(|parallelism| u32::try_from(parallelism).ok())
+                .filter(|parallelism| *parallelism > 0)
+                .ok_or_else(|| invalid("parallelism", "expected a positive integer".to_string()))?,
+        ),
+    };
+
+    let on_error = node
+        .data
+        .get("onError")
+        .filter(|on_error| !on_error.is_null())
+        .map(|on_error| serde_json::from_value(on_error.clone()))
+        .transpose()
+        .map_err(|e| invalid("onError", e.to_string()))?
+        .unwrap_or_default();
+
+    let initial = match kind {
+        IterationKind::Reduce => node
+            .data
+            .get("initial")
+            .map(|initial| push_constant(program, Value::from(initial.clone()))),
+        _ => None,
+    };
+
+    Ok(Op::Iterate {
+        kind,
+        graph,
+        parallelism,
+        on_error,
+        initial,
+    })
+}
+
+fn constant_arguments(node: &FlowGraphNode, program: &mut Program) -> Option<ConstantId> {
+    node.data
+        .get("arguments")
+        .map(|arguments| push_constant(program, Value::from(arguments.clone())))
+}
+
+fn retry_policy(node: &FlowGraphNode) -> Result<Option<RetryPolicy>, CompileError> {
+    node.data
+        .get("retry")
+        .filter(|retry| !retry.is_null())
+        .map(|retry| {
+            serde_json::from_value(retry.clone()).map_err(|e| {
+                CompileError::at(
+                    &node.id,
+                    CompileErrorKind::InvalidField {
+                        field: "retry".to_string(),
+                        message: e.to_string(),
+                    },
+                )
+            })
+        })
+        .transpose()
+}
+
+fn required_str<'a>(node: &'a FlowGraphNode, field: &str) -> Result<&'a str, CompileError> {
+    node.data
+        .get(field)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| {
+            CompileError::at(
+                &node.id,
+                CompileErrorKind::MissingField {
+                    field: field.to_string(),
+                },
+            )
+        })
+}
+
+fn intern_function(program: &mut Program, function: FunctionRef) -> usize {
+    if let Some(existing) = program.functions.iter().position(|f| *f == function) {
+        return existing;
+    }
+
+    program.functions.push(function);
+    program.functions.len() - 1
+}
+
+fn intern_script(program: &mut Program, script: Script) -> ScriptId {
+    if let Some(existing) = program.scripts.iter().position(|s| *s == script) {
+        return existing;
+    }
+
+    program.scripts.push(script);
+    program.scripts.len() - 1
+}
+
+fn push_constant(program: &mut Program, value: Value) -> ConstantId {
+    program.constants.push(value);
+    program.constants.len() - 1
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use flow_rt_shared::{
+        schemas::{FlowGraph, FlowGraphNode},
+        value::{Value, ValueType},
+    };
+    use serde_json::json;
+
+    use crate::{
+        compiler::{compile, compile_with_scripts},
+        error::CompileErrorKind,
+        ir::{ItemErrorPolicy, IterationKind, Op},
+        script::ScriptError,
+        testing::{Catalog, edge, function_node, graph, node, port_edge, variable},
+    };
+
+    fn fetch(id: &str) -> FlowGraphNode {
+        function_node(id, "http-module", "fetch")
+    }
+
+    #[test]
+    fn compile_should_order_instructions_after_their_inputs() {
+        let graph = graph(
+            vec![
+                fetch("b"),
+                fetch("a"),
+                node("start", "startNode", json!({})),
+            ],
+            vec![edge("start", "a"), edge("a", "b")],
+        );
+
+        let program = compile(&graph, &Catalog).expect("graph should compile");
+        let order = program
+            .instructions
+            .iter()
+            .map(|i| i.node_id.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(order, vec!["start", "a", "b"]);
+        assert_eq!(program.entry_points, vec![0]);
+        assert_eq!(program.functions.len(), 1);
+        assert_eq!(program.instructions[2].inputs[0].source, 1);
+    }
+
+    #[test]
+    fn compile_should_point_at_unknown_function() {
+        let graph = graph(
+            vec![
+                node("start", "startNode", json!({})),
+                node(
+                    "nmap",
+                    "foreignFunctionNode",
+                    json!({ "pluginName": "test-nmap-module", "functionName": "nmap_run" }),
+                ),
+            ],
+            vec![edge("start", "nmap")],
+        );
+
+        let error = compile(&graph, &Catalog).unwrap_err();
+        assert_eq!(error.node_id.as_deref(), Some("nmap"));
+        assert!(matches!(
+            error.kind,
+            CompileErrorKind::UnknownFunction { .. }
+        ));
+    }
+
+    #[test]
+    fn compile_should_embed_scripts_and_report_their_syntax_errors() {
+        let graph = graph(
+            vec![
+                node("start", "startNode", json!({})),
+                node("double", "scriptNode", json!({ "scriptName": "double" })),
+            ],
+            vec![edge("start", "double")],
+        );
+
+        let mut scripts = BTreeMap::from([("double".to_string(), "input * 2".to_string())]);
+        let program = compile_with_scripts(&graph, &Catalog, &scripts).unwrap();
+        assert!(matches!(
+            program.instructions[1].op,
+            Op::Script { script: 0, .. }
+        ));
+        assert_eq!(program.scripts[0].source, "input * 2");
+
+        scripts.insert("double".into(), "let x = ;\ninput * 2".into());
+        let error = compile_with_scripts(&graph, &Catalog, &scripts).unwrap_err();
+        assert_eq!(error.node_id.as_deref(), Some("double"));
+        assert!(matches!(
+            error.kind,
+            CompileErrorKind::InvalidScript {
+                error: ScriptError {
+                    line: Some(1),
+                    column: Some(9),
+                    ..
+                },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn compile_should_reject_cycles() {
+        let graph = graph(
+            vec![
+                node("start", "startNode", json!({})),
+                fetch("a"),
+                fetch("b"),
+            ],
+            vec![edge("start", "a"), edge("a", "b"), edge("b", "a")],
+        );
+
+        let error = compile(&graph, &Catalog).unwrap_err();
+        assert_eq!(error.node_id.as_deref(), Some("a"));
+        assert_eq!(
+            error.kind,
+            CompileErrorKind::Cycle {
+                path: vec!["a".into(), "b".into(), "a".into()]
+            }
+        );
+    }
+
+    #[test]
+    fn compile_should_keep_constant_arguments() {
+        let graph = graph(
+            vec![
+                node("start", "startNode", json!({})),
+                node(
+                    "a",
+                    "foreignFunctionNode",
+                    json!({
+                        "pluginName": "http-module",
+                        "functionName": "fetch",
+                        "arguments": { "url": "https://example.com" }
+                    }),
+                ),
+            ],
+            vec![edge("start", "a")],
+        );
+
+        let program = compile(&graph, &Catalog).unwrap();
+        let Op::Call { arguments, .. } = &program.instructions[1].op else {
+            panic!("expected a call instruction");
+        };
+
+        let constant = program.constant(arguments.unwrap()).unwrap();
+        assert_eq!(
+            constant.get("url"),
+            Some(&Value::from("https://example.com"))
+        );
+    }
+
+    #[test]
+    fn compile_should_bind_edges_to_ports() {
+        let fetch_with_ports = node(
+            "fetch",
+            "foreignFunctionNode",
+            json!({
+                "pluginName": "http-module",
+                "functionName": "fetch",
+                "inputs": [
+                    { "id": "url", "type": "string", "required": true },
+                    { "id": "properties", "type": "object" }
+                ]
+            }),
+        );
+
+        let mut to_url = edge("start", "fetch");
+        to_url.source_handle = Some("url".into());
+        to_url.target_handle = Some("url".into());
+
+        let graph = graph(
+            vec![node("start", "startNode", json!({})), fetch_with_ports],
+            vec![to_url.clone()],
+        );
+
+        let program = compile(&graph, &Catalog).unwrap();
+        let binding = &program.instructions[1].inputs[0];
+        assert_eq!(binding.target_port.as_deref(), Some("url"));
+        assert_eq!(program.instructions[1].input_ports.len(), 2);
+
+        let mut graph = graph;
+        to_url.target_handle = Some("body".into());
+        graph.edges = Some(vec![to_url]);
+
+        let error = compile(&graph, &Catalog).unwrap_err();
+        assert_eq!(error.node_id.as_deref(), Some("fetch"));
+        assert!(matches!(error.kind, CompileErrorKind::UnknownPort { .. }));
+    }
+
+    #[test]
+    fn edges_without_handles_should_still_load() {
+        let graph = serde_json::from_value::<FlowGraph>(json!({
+            "nodes": [
+                { "id": "start", "type": "startNode", "position": { "x": 0, "y": 0 }, "data": {} }
+            ],
+            "edges": [
+                { "id": "e", "type": "custom", "source": "start", "target": "start" }
+            ]
+        }))
+        .unwrap();
+
+        let edge = &graph.edges.unwrap()[0];
+        assert_eq!(edge.source_handle, None);
+        assert_eq!(edge.target_handle, None);
+    }
+
+    #[test]
+    fn compile_should_bind_entry_schemas_to_ports_and_compile_triggers_to_graph_calls() {
+        let graph = graph(
+            vec![
+                node(
+                    "entry",
+                    "fnEntry",
+                    json!({ "schema": { "count": "number" } }),
+                ),
+                node("call", "fnTrigger", json!({ "graphName": "other" })),
+            ],
+            vec![edge("entry", "call")],
+        );
+
+        let program = compile(&graph, &Catalog).unwrap();
+
+        assert_eq!(program.instructions[0].input_ports[0].id, "count");
+        assert_eq!(
+            program.instructions[1].op,
+            Op::CallGraph {
+                graph: "other".into(),
+                arguments: None
+            }
+        );
+    }
+
+    #[test]
+    fn compile_should_require_edges_leaving_branches_to_name_their_branch() {
+        let graph = |to_fetch| {
+            graph(
+                vec![
+                    node("start", "startNode", json!({})),
+                    node("if", "ifNode", json!({})),
+                    fetch("fetch"),
+                ],
+                vec![edge("start", "if"), to_fetch],
+            )
+        };
+
+        let error = compile(&graph(edge("if", "fetch")), &Catalog).unwrap_err();
+        assert!(matches!(error.kind, CompileErrorKind::MissingBranch { .. }));
+
+        let error = compile(&graph(port_edge("if", "maybe", "fetch")), &Catalog).unwrap_err();
+        assert!(matches!(error.kind, CompileErrorKind::UnknownPort { .. }));
+
+        let program = compile(&graph(port_edge("if", "else", "fetch")), &Catalog).unwrap();
+        assert_eq!(
+            program.instructions[2].inputs[0].source_port.as_deref(),
+            Some("else")
+        );
+    }
+
+    #[test]
+    fn compile_should_name_switch_ports_after_their_cases() {
+        let graph = |cases: serde_json::Value| {
+            graph(
+                vec![
+                    node("start", "startNode", json!({})),
+                    node("switch", "switchNode", json!({ "cases": cases })),
+                ],
+                vec![edge("start", "switch")],
+            )
+        };
+
+        let program = compile(&graph(json!(["on", 1, true])), &Catalog).unwrap();
+        let Op::Switch { cases } = &program.instructions[1].op else {
+            panic!("expected a switch, got {:?}", program.instructions[1].op);
+        };
+        let ports = cases
+            .iter()
+            .map(|case| case.port.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ports, ["on", "1", "true"]);
+
+        let error = compile(&graph(json!(["on", "on"])), &Catalog).unwrap_err();
+        assert!(matches!(error.kind, CompileErrorKind::InvalidField { .. }));
+    }
+
+    #[test]
+    fn compile_should_check_iteration_settings() {
+        let graph = |data: serde_json::Value| {
+            graph(
+                vec![
+                    node("start", "startNode", json!({})),
+                    node("map", "mapNode", data),
+                ],
+                vec![edge("start", "map")],
+            )
+        };
+
+        let program = compile(
+            &graph(json!({ "graphName": "body", "parallelism": 4, "onError": "collect" })),
+            &Catalog,
+        )
+        .unwrap();
+        assert_eq!(
+            program.instructions[1].op,
+            Op::Iterate {
+                kind: IterationKind::Map,
+                graph: "body".into(),
+                parallelism: Some(4),
+                on_error: ItemErrorPolicy::Collect,
+                initial: None,
+            }
+        );
+
+        for data in [
+            json!({ "graphName": "body", "parallelism": 0 }),
+            json!({ "graphName": "body", "onError": "ignore" }),
+        ] {
+            let error = compile(&graph(data), &Catalog).unwrap_err();
+            assert!(matches!(error.kind, CompileErrorKind::InvalidField { .. }));
+        }
+    }
+
+    #[test]
+    fn compile_should_resolve_variables_declared_on_the_graph() {
+        let graph = |name: &str, default: serde_json::Value| FlowGraph {
+            variables: Some(vec![variable("retries", ValueType::Number, default)]),
+            ..graph(
+                vec![
+                    node("start", "startNode", json!({})),
+                    node("get", "getVariable", json!({ "variableName": name })),
+                ],
+                vec![edge("start", "get")],
+            )
+        };
+
+        let program = compile(&graph("retries", json!("3")), &Catalog).unwrap();
+        assert_eq!(program.instructions[1].op, Op::GetVariable { variable: 0 });
+        assert_eq!(program.variables[0].default, Value::from(3));
+
+        let error = compile(&graph("attempts", json!(3)), &Catalog).unwrap_err();
+        assert!(matches!(
+            error.kind,
+            CompileErrorKind::UnknownVariable { .. }
+        ));
+
+        let error = compile(&graph("retries", json!("many")), &Catalog).unwrap_err();
+        assert!(matches!(
+            error.kind,
+            CompileErrorKind::InvalidVariable { .. }
+        ));
+    }
+}
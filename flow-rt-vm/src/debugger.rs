@@ -0,0 +1,208 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    pin::pin,
+    sync::{Mutex, MutexGuard},
+};
+
+use flow_rt_shared::value::Value;
+use serde::Serialize;
+use tokio::sync::Notify;
+
+use crate::interpreter::CallFrame;
+
+/// Why a node was suspended before running.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SuspendReason {
+    Breakpoint,
+    Paused,
+    Step,
+}
+
+/// A node waiting for the debugger to let it run.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Suspension {
+    #[serde(skip)]
+    ticket: u64,
+    pub node_id: String,
+    pub call_stack: Vec<CallFrame>,
+    pub reason: SuspendReason,
+}
+
+/// The state of a debugged run, as shown to the user.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugSnapshot {
+    /// Nodes currently suspended, in the order they were reached.
+    pub suspended: Vec<Suspension>,
+    /// The last value that flowed over every edge, keyed by edge id.
+    pub edges: BTreeMap<String, Value>,
+    pub breakpoints: BTreeSet<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Mode {
+    #[default]
+    Running,
+    /// Suspend the next node that is reached.
+    Paused,
+    /// Suspend the next node nested at most `max_depth` function graph calls
+    /// deep, or the next node at all when `None`.
+    Step { max_depth: Option<usize> },
+}
+
+#[derive(Default)]
+struct DebugState {
+    breakpoints: BTreeSet<String>,
+    mode: Mode,
+    suspended: Vec<Suspension>,
+    next_ticket: u64,
+    edges: BTreeMap<String, Value>,
+}
+
+/// Controls a run from the outside: breakpoints, pausing, stepping and
+/// inspecting the values on edges.
+///
+/// The interpreter checks in with the debugger before every node. A node
+/// that has to stop waits on the debugger without blocking its thread, so
+/// the rest of the runtime keeps going. Once a node is suspended the whole
+/// run pauses: every other node reaching its checkpoint is suspended as well.
+#[derive(Default)]
+pub struct Debugger {
+    state: Mutex<DebugState>,
+    changed: Notify,
+}
+
+impl Debugger {
+    pub fn new(breakpoints: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            state: Mutex::new(DebugState {
+                breakpoints: breakpoints.into_iter().collect(),
+                ..Default::default()
+            }),
+            changed: Notify::new(),
+        }
+    }
+
+    pub fn set_breakpoints(&self, breakpoints: impl IntoIterator<Item = String>) {
+        self.lock().breakpoints = breakpoints.into_iter().collect();
+    }
+
+    /// Suspends the next node that is about to run.
+    pub fn pause(&self) {
+        self.lock().mode = Mode::Paused;
+    }
+
+    /// Lets every suspended node run and runs on until the next breakpoint.
+    pub fn resume(&self) {
+        let mut state = self.lock();
+        state.mode = Mode::Running;
+        state.suspended.clear();
+        drop(state);
+
+        self.changed.notify_waiters();
+    }
+
+    /// Runs the first suspended node, then stops at the next node of the same
+    /// or an outer graph, stepping over any function graph the node calls.
+    pub fn step_over(&self) {
+        self.step(false);
+    }
+
+    /// Runs the first suspended node, then stops at the very next node, which
+    /// is inside the called function graph when the node calls one.
+    pub fn step_into(&self) {
+        self.step(true);
+    }
+
+    fn step(&self, into: bool) {
+        let mut state = self.lock();
+        let released = (!state.suspended.is_empty()).then(|| state.suspended.remove(0));
+        let max_depth = match released {
+            Some(released) if !into => Some(released.call_stack.len()),
+            _ => None,
+        };
+        state.mode = Mode::Step { max_depth };
+        drop(state);
+
+        self.changed.notify_waiters();
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        !self.lock().suspended.is_empty()
+    }
+
+    pub fn snapshot(&self) -> DebugSnapshot {
+        let state = self.lock();
+        DebugSnapshot {
+            suspended: state.suspended.clone(),
+            edges: state.edges.clone(),
+            breakpoints: state.breakpoints.clone(),
+        }
+    }
+
+    pub(crate) fn record_edge(&self, edge_id: &str, value: Value) {
+        self.lock().edges.insert(edge_id.to_string(), value);
+    }
+
+    /// Called before `node_id` runs. Returns right away unless the node has
+    /// to stop, in which case `on_suspend` is called and the node waits until
+    /// the debugger releases it.
+    pub(crate) async fn checkpoint(
+        &self,
+        node_id: &str,
+        call_stack: &[CallFrame],
+        on_suspend: impl FnOnce(&Suspension),
+    ) {
+        let suspension = {
+            let mut state = self.lock();
+            let reason = if state.breakpoints.contains(node_id) {
+                SuspendReason::Breakpoint
+            } else {
+                match state.mode {
+                    Mode::Running => return,
+                    Mode::Paused => SuspendReason::Paused,
+                    Mode::Step {
+                        max_depth: Some(depth),
+                    } if call_stack.len() > depth => return,
+                    Mode::Step { .. } => SuspendReason::Step,
+                }
+            };
+
+            let suspension = Suspension {
+                ticket: state.next_ticket,
+                node_id: node_id.to_string(),
+                call_stack: call_stack.to_vec(),
+                reason,
+            };
+            state.next_ticket += 1;
+            state.mode = Mode::Paused;
+            state.suspended.push(suspension.clone());
+            suspension
+        };
+
+        on_suspend(&suspension);
+
+        loop {
+            let mut released = pin!(self.changed.notified());
+            // Registered before checking, so a release in between isn't missed.
+            released.as_mut().enable();
+
+            if !self
+                .lock()
+                .suspended
+                .iter()
+                .any(|s| s.ticket == suspension.ticket)
+            {
+                return;
+            }
+
+            released.await;
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, DebugState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
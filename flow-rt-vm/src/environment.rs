@@ -0,0 +1,179 @@
+use std::{collections::BTreeMap, path::Path};
+
+use flow_rt_shared::value::Value;
+use serde::{Deserialize, Serialize};
+
+use crate::references::resolve_references;
+
+const ENVIRONMENT_NAMESPACE: &str = "env";
+
+/// The named environments of a project, read from the `[environments]`
+/// table of `Flow.toml`:
+///
+/// ```toml
+/// [environments]
+/// current = "dev"
+/// dotenv = ".env"
+/// inherit = ["CI"]
+///
+/// [environments.profiles.dev]
+/// API_URL = "http://localhost:8080"
+///
+/// [environments.profiles.prod]
+/// API_URL = "https://api.example.com"
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Environments {
+    /// The profile runs use unless another one is selected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<String>,
+    /// A `.env` file, relative to the project, every profile falls back to.
+    /// A missing file is ignored, so it can be left out of version control.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dotenv: Option<String>,
+    /// Variables of the process environment runs may reference. No others
+    /// are, as they'd end up in the traces and history of those runs.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub inherit: Vec<String>,
+    /// The variables of every profile, by profile name.
+    pub profiles: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Environments {
+    /// The environment of the project at `project` with the profile
+    /// `selected`, or the current one when none is selected.
+    ///
+    /// A variable of the profile takes precedence over an inherited one of the
+    /// process environment, which takes precedence over one of the `.env` file.
+    pub fn load(&self, project: &Path, selected: Option<&str>) -> Result<Environment, String> {
+        let name = selected.or(self.current.as_deref());
+        let profile = match name {
+            Some(name) => Some(
+                self.profiles
+                    .get(name)
+                    .ok_or_else(|| format!("Environment {name} is not defined in Flow.toml"))?,
+            ),
+            None => None,
+        };
+
+        let mut variables = match &self.dotenv {
+            Some(dotenv) => read_dotenv(&project.join(dotenv))?,
+            None => BTreeMap::new(),
+        };
+        variables.extend(
+            self.inherit
+                .iter()
+                .filter_map(|name| Some((name.clone(), std::env::var(name).ok()?))),
+        );
+        variables.extend(
+            profile
+                .into_iter()
+                .flatten()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+
+        Ok(Environment {
+            name: name.map(str::to_string),
+            variables,
+        })
+    }
+}
+
+/// The variables `${env.NAME}` references in node arguments resolve to.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Environment {
+    /// The profile the variables come from, if any.
+    pub name: Option<String>,
+    pub variables: BTreeMap<String, String>,
+}
+
+impl Environment {
+    /// Replaces every `${env.NAME}` in the strings of `value` with the
+    /// variable `NAME`. Fails with the name of the first variable that isn't
+    /// set.
+    pub(crate) fn resolve(&self, value: &Value) -> Result<Value, String> {
+        resolve_references(value, ENVIRONMENT_NAMESPACE, |name| {
+            self.variables.get(name).cloned()
+        })
+    }
+}
+
+fn read_dotenv(path: &Path) -> Result<BTreeMap<String, String>, String> {
+    let entries = match dotenvy::from_path_iter(path) {
+        Ok(entries) => entries,
+        Err(e) if e.not_found() => return Ok(BTreeMap::new()),
+        Err(e) => return Err(format!("{path:?} could not be read: {e}")),
+    };
+
+    // Unlike `dotenvy::from_path`, this leaves the process environment alone.
+    entries
+        .map(|entry| entry.map_err(|e| format!("{path:?} is not a valid .env file: {e}")))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde_json::json;
+
+    use crate::environment::Environments;
+
+    #[test]
+    fn profiles_should_override_the_dotenv_file() {
+        let project = std::env::temp_dir().join(format!("flow-rt-env-{}", std::process::id()));
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(
+            project.join(".env"),
+            "FLOW_RT_TEST_URL=http://localhost\nFLOW_RT_TEST_USER=admin\n",
+        )
+        .unwrap();
+
+        let environments = Environments {
+            current: Some("dev".into()),
+            dotenv: Some(".env".into()),
+            inherit: vec!["PATH".into()],
+            profiles: BTreeMap::from([
+                ("dev".into(), BTreeMap::new()),
+                (
+                    "prod".into(),
+                    BTreeMap::from([("FLOW_RT_TEST_URL".into(), "https://example.com".into())]),
+                ),
+            ]),
+        };
+
+        let dev = environments.load(&project, None).unwrap();
+        let prod = environments.load(&project, Some("prod")).unwrap();
+        let missing = environments.load(&project, Some("staging"));
+        std::fs::remove_dir_all(&project).unwrap();
+
+        assert_eq!(dev.name.as_deref(), Some("dev"));
+        assert_eq!(
+            dev.resolve(&json!("${env.FLOW_RT_TEST_URL}/users").into()),
+            Ok(json!("http://localhost/users").into())
+        );
+        assert_eq!(
+            prod.resolve(
+                &json!({ "url": "${env.FLOW_RT_TEST_URL}", "user": "${env.FLOW_RT_TEST_USER}" })
+                    .into()
+            ),
+            Ok(json!({ "url": "https://example.com", "user": "admin" }).into())
+        );
+        assert_eq!(
+            prod.resolve(&json!("${env.FLOW_RT_TEST_MISSING}").into()),
+            Err("FLOW_RT_TEST_MISSING".to_string())
+        );
+        assert!(missing.is_err());
+
+        // Only inherited variables of the process environment resolve.
+        assert_eq!(
+            dev.resolve(&json!("${env.PATH}").into()),
+            Ok(json!(std::env::var("PATH").unwrap()).into())
+        );
+        assert_eq!(
+            dev.resolve(&json!("${env.HOME}").into()),
+            Err("HOME".to_string())
+        );
+    }
+}
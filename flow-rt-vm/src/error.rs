@@ -0,0 +1,319 @@
+use std::fmt::Display;
+
+use flow_rt_shared::value::ValueType;
+use serde::{Deserialize, Serialize};
+
+use crate::{interpreter::CallFrame, script::ScriptError};
+
+/// An error raised while lowering a `FlowGraph` into a [`crate::ir::Program`].
+///
+/// `node_id` points at the offending node when the error can be attributed to one,
+/// so the editor can highlight it.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CompileError {
+    pub node_id: Option<String>,
+    pub kind: CompileErrorKind,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum CompileErrorKind {
+    EmptyGraph,
+    MissingEntryPoint,
+    DuplicateNode,
+    UnknownNodeType { node_type: String },
+    UnsupportedNodeType { node_type: String },
+    DanglingEdge { edge_id: String },
+    UnknownPort { edge_id: String, port: String },
+    // An edge leaves an `ifNode` or `switchNode` without naming the port it is taken from.
+    MissingBranch { edge_id: String },
+    MissingField { field: String },
+    InvalidField { field: String, message: String },
+    UnknownFunction { plugin: String, function: String },
+    UnknownScript { script: String, message: String },
+    InvalidScript { script: String, error: ScriptError },
+    // A `getVariable` or `setVariable` node names a variable the graph doesn't declare.
+    UnknownVariable { variable: String },
+    InvalidVariable { variable: String, message: String },
+    Cycle { path: Vec<String> },
+}
+
+impl CompileError {
+    pub fn new(kind: CompileErrorKind) -> Self {
+        Self {
+            node_id: None,
+            kind,
+        }
+    }
+
+    pub fn at(node_id: impl Into<String>, kind: CompileErrorKind) -> Self {
+        Self {
+            node_id: Some(node_id.into()),
+            kind,
+        }
+    }
+}
+
+impl Display for CompileErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompileErrorKind::EmptyGraph => write!(f, "graph has no nodes"),
+            CompileErrorKind::MissingEntryPoint => write!(f, "graph has no entry point"),
+            CompileErrorKind::DuplicateNode => write!(f, "node id is used more than once"),
+            CompileErrorKind::UnknownNodeType { node_type } => {
+                write!(f, "unknown node type `{node_type}`")
+            }
+            CompileErrorKind::UnsupportedNodeType { node_type } => {
+                write!(f, "node type `{node_type}` cannot be executed yet")
+            }
+            CompileErrorKind::DanglingEdge { edge_id } => {
+                write!(f, "edge `{edge_id}` references a node that does not exist")
+            }
+            CompileErrorKind::UnknownPort { edge_id, port } => {
+                write!(f, "edge `{edge_id}` uses undeclared port `{port}`")
+            }
+            CompileErrorKind::MissingBranch { edge_id } => {
+                write!(
+                    f,
+                    "edge `{edge_id}` leaves a branch node without naming its branch"
+                )
+            }
+            CompileErrorKind::MissingField { field } => {
+                write!(f, "node data is missing `{field}`")
+            }
+            CompileErrorKind::InvalidField { field, message } => {
+                write!(f, "node data field `{field}` is invalid: {message}")
+            }
+            CompileErrorKind::UnknownFunction { plugin, function } => {
+                write!(f, "no loaded plugin exposes `{plugin}::{function}`")
+            }
+            CompileErrorKind::UnknownScript { script, message } => {
+                write!(f, "can't load script `{script}`: {message}")
+            }
+            CompileErrorKind::InvalidScript { script, error } => {
+                write!(f, "script `{script}` doesn't compile: {error}")
+            }
+            CompileErrorKind::UnknownVariable { variable } => {
+                write!(f, "graph doesn't declare a variable named `{variable}`")
+            }
+            CompileErrorKind::InvalidVariable { variable, message } => {
+                write!(f, "variable `{variable}` is invalid: {message}")
+            }
+            CompileErrorKind::Cycle { path } => {
+                write!(f, "graph loops back on itself: {}", path.join(" -> "))
+            }
+        }
+    }
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.node_id {
+            Some(node_id) => write!(f, "{node_id}: {}", self.kind),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// An error raised while executing a [`crate::ir::Program`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RuntimeError {
+    pub node_id: Option<String>,
+    pub kind: RuntimeErrorKind,
+    /// Function graph calls that led to the failing node, outermost first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub call_stack: Vec<CallFrame>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum RuntimeErrorKind {
+    /// The program references a function or constant it does not contain.
+    InvalidProgram,
+    FunctionFailed {
+        message: String,
+    },
+    MissingInput {
+        port: String,
+    },
+    /// A node declaring several input ports, or receiving values on named
+    /// ports and without a port, received a value that isn't an object.
+    InvalidInput {
+        found: ValueType,
+    },
+    TypeMismatch {
+        port: String,
+        message: String,
+    },
+    /// A task executing part of the graph panicked or was aborted.
+    TaskFailed {
+        message: String,
+    },
+    /// A called function graph couldn't be loaded.
+    UnknownGraph {
+        graph: String,
+        message: String,
+    },
+    /// Function graphs called each other more than `limit` levels deep.
+    RecursionLimit {
+        limit: usize,
+    },
+    /// Event graphs fired events starting each other more than `limit` events deep.
+    EventDepthLimit {
+        event: String,
+        limit: usize,
+    },
+    /// The run was cancelled before the node finished.
+    Cancelled,
+    /// The node ran longer than its timeout.
+    TimedOut {
+        timeout_ms: u64,
+    },
+    /// A script failed while running. `line` and `column` start at 1.
+    ScriptFailed {
+        script: String,
+        message: String,
+        line: Option<u32>,
+        column: Option<u32>,
+    },
+    /// A replayed run reached a plugin call the recording has no outcome for.
+    NotRecorded,
+    /// Node arguments reference a secret the secret store doesn't hold.
+    UnknownSecret {
+        name: String,
+    },
+    /// Node arguments reference a variable the environment of the run doesn't set.
+    UnknownEnvironmentVariable {
+        name: String,
+    },
+}
+
+impl RuntimeError {
+    pub fn new(kind: RuntimeErrorKind) -> Self {
+        Self {
+            node_id: None,
+            kind,
+            call_stack: vec![],
+        }
+    }
+
+    pub fn at(node_id: impl Into<String>, kind: RuntimeErrorKind) -> Self {
+        Self {
+            node_id: Some(node_id.into()),
+            kind,
+            call_stack: vec![],
+        }
+    }
+}
+
+impl RuntimeErrorKind {
+    /// Identifies the kind of error, as used by [`crate::retry::RetryPolicy::retry_on`].
+    /// Matches the `type` the error is serialized with.
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuntimeErrorKind::InvalidProgram => "invalidProgram",
+            RuntimeErrorKind::FunctionFailed { .. } => "functionFailed",
+            RuntimeErrorKind::MissingInput { .. } => "missingInput",
+            RuntimeErrorKind::InvalidInput { .. } => "invalidInput",
+            RuntimeErrorKind::TypeMismatch { .. } => "typeMismatch",
+            RuntimeErrorKind::TaskFailed { .. } => "taskFailed",
+            RuntimeErrorKind::UnknownGraph { .. } => "unknownGraph",
+            RuntimeErrorKind::RecursionLimit { .. } => "recursionLimit",
+            RuntimeErrorKind::EventDepthLimit { .. } => "eventDepthLimit",
+            RuntimeErrorKind::Cancelled => "cancelled",
+            RuntimeErrorKind::TimedOut { .. } => "timedOut",
+            RuntimeErrorKind::ScriptFailed { .. } => "scriptFailed",
+            RuntimeErrorKind::NotRecorded => "notRecorded",
+            RuntimeErrorKind::UnknownSecret { .. } => "unknownSecret",
+            RuntimeErrorKind::UnknownEnvironmentVariable { .. } => "unknownEnvironmentVariable",
+        }
+    }
+}
+
+impl Display for RuntimeErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeErrorKind::InvalidProgram => write!(f, "program is malformed"),
+            RuntimeErrorKind::FunctionFailed { message } => {
+                write!(f, "function call failed: {message}")
+            }
+            RuntimeErrorKind::MissingInput { port } => {
+                write!(f, "required input `{port}` has no value")
+            }
+            RuntimeErrorKind::InvalidInput { found } => {
+                write!(
+                    f,
+                    "expected an object with a field per input port, found {found}"
+                )
+            }
+            RuntimeErrorKind::TypeMismatch { port, message } => {
+                write!(f, "input `{port}` has the wrong type: {message}")
+            }
+            RuntimeErrorKind::TaskFailed { message } => {
+                write!(f, "execution task failed: {message}")
+            }
+            RuntimeErrorKind::UnknownGraph { graph, message } => {
+                write!(f, "can't call function graph `{graph}`: {message}")
+            }
+            RuntimeErrorKind::RecursionLimit { limit } => {
+                write!(f, "function graphs nested more than {limit} calls deep")
+            }
+            RuntimeErrorKind::EventDepthLimit { event, limit } => {
+                write!(
+                    f,
+                    "can't fire `{event}`: event graphs started each other {limit} events deep"
+                )
+            }
+            RuntimeErrorKind::Cancelled => write!(f, "run was cancelled"),
+            RuntimeErrorKind::TimedOut { timeout_ms } => {
+                write!(f, "node did not finish within {timeout_ms} ms")
+            }
+            RuntimeErrorKind::ScriptFailed {
+                script,
+                message,
+                line,
+                column,
+            } => {
+                let error = ScriptError {
+                    message: message.clone(),
+                    line: *line,
+                    column: *column,
+                };
+                write!(f, "script `{script}` failed: {error}")
+            }
+            RuntimeErrorKind::NotRecorded => {
+                write!(f, "the recorded run has no outcome for this call")
+            }
+            RuntimeErrorKind::UnknownSecret { name } => {
+                write!(f, "no secret named `{name}` is stored")
+            }
+            RuntimeErrorKind::UnknownEnvironmentVariable { name } => {
+                write!(f, "no variable named `{name}` is set in the environment")
+            }
+        }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.node_id {
+            Some(node_id) => write!(f, "{node_id}: {}", self.kind)?,
+            None => write!(f, "{}", self.kind)?,
+        }
+
+        if !self.call_stack.is_empty() {
+            let graphs = self
+                .call_stack
+                .iter()
+                .map(|frame| frame.graph.as_str())
+                .collect::<Vec<_>>();
+            write!(f, " (in {})", graphs.join(" -> "))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for RuntimeError {}
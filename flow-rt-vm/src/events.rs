@@ -0,0 +1,428 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock, Weak},
+};
+
+use flow_rt_shared::value::Value;
+use serde::Serialize;
+
+use crate::{
+    environment::Environment,
+    error::{RuntimeError, RuntimeErrorKind},
+    interpreter::{FunctionHost, Interpreter, RunOutput},
+    ir::Program,
+    script::ScriptEngine,
+    secrets::SecretStore,
+};
+
+/// How many events in a row may start event graphs that fire the next one.
+/// Keeps event graphs triggering themselves, directly or through each other,
+/// from starting runs forever.
+pub const MAX_EVENT_DEPTH: usize = 32;
+
+/// Receives the events fired by `eventTrigger` nodes during a run.
+pub trait EventSink: Send + Sync {
+    /// Fires `event` without waiting for its listeners to finish. `depth` is
+    /// the event depth of the run firing it, see [`Interpreter::with_event_depth`].
+    fn emit(&self, event: &str, payload: Value, depth: usize);
+}
+
+/// Told about the event graphs started by `eventTrigger` nodes, which have no
+/// caller waiting for their outcome.
+pub trait EventRunObserver: Send + Sync {
+    fn finished(&self, event: &str, run: &EventRun);
+}
+
+/// The outcome of one event graph started by [`EventBus::fire`].
+#[derive(Serialize, Clone, Debug)]
+pub struct EventRun {
+    pub graph: String,
+    pub result: Result<RunOutput, RuntimeError>,
+}
+
+/// Dispatches named events to the compiled event graphs of a project.
+///
+/// Graphs are registered by name; firing an event starts every registered
+/// graph with an `eventListener` triggered by it. Event graphs can fire
+/// further events themselves, which are dispatched through the same bus.
+pub struct EventBus {
+    host: Arc<dyn FunctionHost>,
+    functions: RwLock<Arc<BTreeMap<String, Arc<Program>>>>,
+    graphs: RwLock<BTreeMap<String, Arc<Program>>>,
+    scripts: RwLock<Arc<ScriptEngine>>,
+    secrets: RwLock<Option<Arc<dyn SecretStore>>>,
+    environment: RwLock<Option<Arc<Environment>>>,
+    observer: RwLock<Option<Arc<dyn EventRunObserver>>>,
+    this: Weak<EventBus>,
+}
+
+impl EventBus {
+    /// Event graphs call plugin functions through `host`.
+    pub fn new(host: Arc<dyn FunctionHost>) -> Arc<Self> {
+        Arc::new_cyclic(|this| Self {
+            host,
+            functions: RwLock::default(),
+            graphs: RwLock::default(),
+            scripts: RwLock::new(ScriptEngine::shared()),
+            secrets: RwLock::new(None),
+            environment: RwLock::new(None),
+            observer: RwLock::new(None),
+            this: this.clone(),
+        })
+    }
+
+    /// Adds `program` to the bus, replacing any graph registered as `graph` before.
+    pub fn register(&self, graph: impl Into<String>, program: Arc<Program>) {
+        self.write().insert(graph.into(), program);
+    }
+
+    pub fn unregister(&self, graph: &str) {
+        self.write().remove(graph);
+    }
+
+    pub fn clear(&self) {
+        self.write().clear();
+    }
+
+    /// Resolves the function graphs called by event graphs started from now on
+    /// from `functions`. Event graphs in progress keep calling the versions
+    /// they started with.
+    pub fn set_functions(&self, functions: Arc<BTreeMap<String, Arc<Program>>>) {
+        *self.functions.write().unwrap_or_else(|e| e.into_inner()) = functions;
+    }
+
+    /// Runs the scripts of event graphs started from now on with `scripts`.
+    pub fn set_scripts(&self, scripts: Arc<ScriptEngine>) {
+        *self.scripts.write().unwrap_or_else(|e| e.into_inner()) = scripts;
+    }
+
+    /// The engine the scripts of event graphs run on.
+    pub fn scripts(&self) -> Arc<ScriptEngine> {
+        self.scripts
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Resolves the secrets referenced by event graphs started from now on from `secrets`.
+    pub fn set_secrets(&self, secrets: Arc<dyn SecretStore>) {
+        *self.secrets.write().unwrap_or_else(|e| e.into_inner()) = Some(secrets);
+    }
+
+    /// The store secrets referenced by event graphs are resolved from, if any.
+    pub fn secrets(&self) -> Option<Arc<dyn SecretStore>> {
+        self.secrets
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Resolves the environment variables referenced by event graphs started
+    /// from now on from `environment`.
+    pub fn set_environment(&self, environment: Arc<Environment>) {
+        *self.environment.write().unwrap_or_else(|e| e.into_inner()) = Some(environment);
+    }
+
+    /// The environment variables referenced by event graphs are resolved from, if any.
+    pub fn environment(&self) -> Option<Arc<Environment>> {
+        self.environment
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Reports the outcome of every event graph started by an `eventTrigger`
+    /// node from now on to `observer`.
+    pub fn set_observer(&self, observer: Arc<dyn EventRunObserver>) {
+        *self.observer.write().unwrap_or_else(|e| e.into_inner()) = Some(observer);
+    }
+
+    fn observer(&self) -> Option<Arc<dyn EventRunObserver>> {
+        self.observer
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Names of the registered graphs listening for `event`.
+    pub fn listeners(&self, event: &str) -> Vec<String> {
+        self.read()
+            .iter()
+            .filter(|(_, program)| program.listens_to(event))
+            .map(|(graph, _)| graph.clone())
+            .collect()
+    }
+
+    /// Starts every graph listening for `event` concurrently, passing `payload`
+    /// to its listeners, and waits for all of them to finish.
+    ///
+    /// Results are returned in graph name order.
+    pub async fn fire(&self, event: &str, payload: Value) -> Vec<EventRun> {
+        self.dispatch(event, payload, 1).await
+    }
+
+    /// Like [`Self::fire`], starting the listeners at event depth `depth`.
+    async fn dispatch(&self, event: &str, payload: Value, depth: usize) -> Vec<EventRun> {
+        let subscribed = self
+            .read()
+            .iter()
+            .filter(|(_, program)| program.listens_to(event))
+            .map(|(graph, program)| (graph.clone(), program.clone()))
+            .collect::<Vec<_>>();
+        let functions = self
+            .functions
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+
+        let handles = subscribed
+            .into_iter()
+            .map(|(graph, program)| {
+                let mut interpreter = Interpreter::new(program, self.host.clone())
+                    .with_graphs(functions.clone())
+                    .with_scripts(self.scripts())
+                    .with_event_depth(depth);
+                if let Some(bus) = self.this.upgrade() {
+                    interpreter = interpreter.with_events(bus);
+                }
+                if let Some(secrets) = self.secrets() {
+                    interpreter = interpreter.with_secrets(secrets);
+                }
+                if let Some(environment) = self.environment() {
+                    interpreter = interpreter.with_environment(environment);
+                }
+
+                let event = event.to_string();
+                let payload = payload.clone();
+                let handle =
+                    tokio::spawn(async move { interpreter.run_event(&event, payload).await });
+                (graph, handle)
+            })
+            .collect::<Vec<_>>();
+
+        let mut runs = Vec::with_capacity(handles.len());
+        for (graph, handle) in handles {
+            let result = handle.await.unwrap_or_else(|e| {
+                Err(RuntimeError::new(RuntimeErrorKind::TaskFailed {
+                    message: e.to_string(),
+                }))
+            });
+            runs.push(EventRun { graph, result });
+        }
+
+        runs
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, BTreeMap<String, Arc<Program>>> {
+        self.graphs.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, BTreeMap<String, Arc<Program>>> {
+        self.graphs.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl EventSink for EventBus {
+    fn emit(&self, event: &str, payload: Value, depth: usize) {
+        let Some(bus) = self.this.upgrade() else {
+            return;
+        };
+
+        let event = event.to_string();
+        tokio::spawn(async move {
+            let runs = bus.dispatch(&event, payload, depth + 1).await;
+            if let Some(observer) = bus.observer() {
+                for run in &runs {
+                    observer.finished(&event, run);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeMap, sync::Arc};
+
+    use flow_rt_shared::value::Value;
+    use serde_json::json;
+    use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+
+    use crate::{
+        error::RuntimeErrorKind,
+        events::{EventBus, EventRun, EventRunObserver, EventSink, MAX_EVENT_DEPTH},
+        interpreter::Interpreter,
+        ir::{EntryKind, FunctionRef, Op, Program},
+        testing::{RecordingHost, instruction},
+    };
+
+    /// `entry -> op`, where `op` receives the value produced by the entry.
+    fn program(entry: EntryKind, op: Op) -> Arc<Program> {
+        Arc::new(Program {
+            instructions: vec![
+                instruction("entry", Op::Entry(entry), vec![], vec![1]),
+                instruction("body", op, vec![0], vec![]),
+            ],
+            functions: vec![FunctionRef {
+                plugin: "test".into(),
+                function: "record".into(),
+            }],
+            entry_points: vec![0],
+            ..Default::default()
+        })
+    }
+
+    fn listener(event: &str) -> Arc<Program> {
+        program(
+            EntryKind::Event { name: event.into() },
+            Op::Call {
+                function: 0,
+                arguments: None,
+                pure: false,
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn fire_should_start_every_listening_graph() {
+        let host = Arc::new(RecordingHost::default());
+        let bus = EventBus::new(host.clone());
+        bus.register("a", listener("programStarted"));
+        bus.register("b", listener("programStarted"));
+        bus.register("c", listener("programEnded"));
+
+        let runs = bus.fire("programStarted", json!({ "id": 1 }).into()).await;
+
+        assert_eq!(
+            runs.iter().map(|r| r.graph.as_str()).collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+        assert!(runs.iter().all(|r| r.result.is_ok()));
+        assert_eq!(host.calls.lock().unwrap().len(), 2);
+        assert_eq!(bus.listeners("programEnded"), ["c"]);
+    }
+
+    #[tokio::test]
+    async fn fire_should_call_the_function_graphs_set_on_the_bus() {
+        let host = Arc::new(RecordingHost::default());
+        let bus = EventBus::new(host.clone());
+        bus.register(
+            "caller",
+            program(
+                EntryKind::Event {
+                    name: "ping".into(),
+                },
+                Op::CallGraph {
+                    graph: "record".into(),
+                    arguments: None,
+                },
+            ),
+        );
+
+        let runs = bus.fire("ping", Value::Null).await;
+        assert!(runs[0].result.is_err());
+
+        let record = program(
+            EntryKind::Function,
+            Op::Call {
+                function: 0,
+                arguments: None,
+                pure: false,
+            },
+        );
+        bus.set_functions(Arc::new(BTreeMap::from([("record".to_string(), record)])));
+
+        let runs = bus.fire("ping", Value::from("payload")).await;
+        assert!(runs[0].result.is_ok());
+        assert_eq!(*host.calls.lock().unwrap(), vec![Value::from("payload")]);
+    }
+
+    #[tokio::test]
+    async fn trigger_should_dispatch_through_the_bus() {
+        let host = Arc::new(RecordingHost::default());
+        let bus = EventBus::new(host.clone());
+        bus.register("listener", listener("ping"));
+
+        let trigger = program(
+            EntryKind::Start,
+            Op::Emit {
+                event: "ping".into(),
+            },
+        );
+        let output = Interpreter::new(trigger, host.clone())
+            .with_events(bus.clone() as Arc<dyn EventSink>)
+            .run(Value::from("payload"))
+            .await
+            .unwrap();
+        assert_eq!(output.outputs["body"], Value::from("payload"));
+
+        // The listener runs detached from the triggering graph.
+        host.wait_for(1).await;
+        assert_eq!(*host.calls.lock().unwrap(), vec![Value::from("payload")]);
+    }
+
+    impl EventRunObserver for UnboundedSender<(String, EventRun)> {
+        fn finished(&self, event: &str, run: &EventRun) {
+            let _ = self.send((event.to_string(), run.clone()));
+        }
+    }
+
+    #[tokio::test]
+    async fn triggered_runs_should_be_reported_to_the_observer() {
+        let bus = EventBus::new(Arc::new(RecordingHost::default()));
+        let (sender, mut finished) = unbounded_channel();
+        bus.set_observer(Arc::new(sender));
+
+        let mut rejecting = Arc::unwrap_or_clone(listener("ping"));
+        rejecting.functions[0].function = "reject".into();
+        bus.register("listener", Arc::new(rejecting));
+
+        bus.emit("ping", Value::Null, 0);
+
+        let (event, run) = finished.recv().await.unwrap();
+        assert_eq!(event, "ping");
+        assert_eq!(run.graph, "listener");
+        assert!(matches!(
+            run.result.unwrap_err().kind,
+            RuntimeErrorKind::FunctionFailed { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn self_triggering_listeners_should_stop_at_the_depth_limit() {
+        let host = Arc::new(RecordingHost::default());
+        let bus = EventBus::new(host.clone());
+        let echo = program(
+            EntryKind::Event {
+                name: "ping".into(),
+            },
+            Op::Emit {
+                event: "ping".into(),
+            },
+        );
+        bus.register("counter", listener("ping"));
+        bus.register("echo", echo.clone());
+
+        let runs = bus.fire("ping", Value::Null).await;
+        assert!(runs.iter().all(|r| r.result.is_ok()));
+
+        // Every dispatch down to the limit starts the counter once, then the
+        // echo fails; the run below shows no dispatch goes past the limit.
+        host.wait_for(MAX_EVENT_DEPTH).await;
+        assert_eq!(host.calls.lock().unwrap().len(), MAX_EVENT_DEPTH);
+
+        let error = Interpreter::new(echo, host.clone())
+            .with_events(bus.clone() as Arc<dyn EventSink>)
+            .with_event_depth(MAX_EVENT_DEPTH)
+            .run_event("ping", Value::Null)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.kind,
+            RuntimeErrorKind::EventDepthLimit {
+                event: "ping".into(),
+                limit: MAX_EVENT_DEPTH
+            }
+        );
+    }
+}
@@ -0,0 +1,2431 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use flow_rt_shared::value::{Value, ValueType};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::Semaphore, task::JoinSet};
+
+use crate::{
+    cancellation::Cancellation,
+    debugger::Debugger,
+    environment::Environment,
+    error::{RuntimeError, RuntimeErrorKind},
+    events::{EventSink, MAX_EVENT_DEPTH},
+    ir::{
+        Binding, ConstantId, DEFAULT_PORT, ELSE_PORT, EntryKind, FunctionRef, GateKind,
+        Instruction, InstructionId, ItemErrorPolicy, IterationKind, Op, Program, THEN_PORT,
+    },
+    memo::{MemoKey, OutputCache},
+    observer::{ExecutionEvent, RunObserver, elapsed_ms},
+    replay::Replay,
+    script::ScriptEngine,
+    secrets::{SecretStore, Secrets},
+    variables::VariableScope,
+};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Executes plugin functions on behalf of the interpreter.
+///
+/// `flow-rt-app` implements this on top of the dyn-rt `PluginRegistry`, tests
+/// can implement it with plain closures.
+pub trait FunctionHost: Send + Sync {
+    fn call<'a>(
+        &'a self,
+        function: &'a FunctionRef,
+        arguments: Value,
+    ) -> BoxFuture<'a, Result<Value, String>>;
+
+    /// A hash of the loaded build of `plugin`, used to tell cached outputs of
+    /// earlier builds apart. Outputs of plugins without one aren't cached.
+    fn plugin_hash(&self, _plugin: &str) -> Option<String> {
+        None
+    }
+}
+
+/// Resolves the function graphs called by `fnTrigger` nodes.
+pub trait GraphLoader: Send + Sync {
+    fn load<'a>(&'a self, graph: &'a str) -> BoxFuture<'a, Result<Arc<Program>, String>>;
+}
+
+/// Compiled graphs by name, for embedders that compile their graphs up front.
+impl GraphLoader for BTreeMap<String, Arc<Program>> {
+    fn load<'a>(&'a self, graph: &'a str) -> BoxFuture<'a, Result<Arc<Program>, String>> {
+        let program = self
+            .get(graph)
+            .cloned()
+            .ok_or_else(|| "no graph with that name".to_string());
+        Box::pin(async move { program })
+    }
+}
+
+/// How deep function graphs may call each other when [`RunOptions::max_call_depth`] isn't set.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 64;
+
+/// A function graph call in progress.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    /// The called function graph.
+    pub graph: String,
+    /// The `fnTrigger` or iteration node in the calling graph.
+    pub node_id: String,
+    /// The index of the item the graph runs for, when called by an iteration node.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<usize>,
+}
+
+/// The values produced by a finished run, keyed by the id of every node that
+/// executed without feeding into another node.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct RunOutput {
+    pub outputs: BTreeMap<String, Value>,
+}
+
+impl RunOutput {
+    /// The result of a function graph call: the value of its only output, or
+    /// all outputs keyed by node id when there are several.
+    pub fn into_value(self) -> Value {
+        if self.outputs.len() == 1 {
+            self.outputs.into_values().next().unwrap_or_default()
+        } else {
+            Value::Object(self.outputs)
+        }
+    }
+}
+
+/// Holds the value produced by every instruction of a single run.
+///
+/// Slots are indexed by [`InstructionId`]; an empty slot means the instruction
+/// has not run (yet).
+#[derive(Debug, Default)]
+pub struct ValueStack {
+    slots: Vec<Option<Value>>,
+}
+
+impl ValueStack {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            slots: vec![None; capacity],
+        }
+    }
+
+    pub fn get(&self, id: InstructionId) -> Option<&Value> {
+        self.slots.get(id).and_then(Option::as_ref)
+    }
+
+    pub fn set(&mut self, id: InstructionId, value: Value) {
+        self.slots[id] = Some(value);
+    }
+
+    /// The value flowing over the edge of `binding`, `Null` while its source hasn't run.
+    pub fn value_of(&self, binding: &Binding) -> Value {
+        let value = self.get(binding.source).cloned().unwrap_or_default();
+        match &binding.source_port {
+            Some(port) => value.get(port).cloned().unwrap_or_default(),
+            None => value,
+        }
+    }
+
+    /// Collects the value flowing into `instruction`.
+    ///
+    /// Edges bound to a named input port are gathered into an object keyed by
+    /// port. Edges without a port keep the pre-port behaviour: a single input
+    /// is passed through as is, multiple inputs are gathered into an array in
+    /// edge order. Inputs without a port that are gathered with named ones
+    /// must be an object, whose fields the named inputs are added to.
+    pub fn gather(&self, instruction: &Instruction) -> Result<Value, RuntimeError> {
+        self.gather_taken(instruction, &[])
+    }
+
+    /// Like [`Self::gather`], leaving out the inputs whose edge wasn't taken,
+    /// flagged by binding index in `untaken`.
+    pub fn gather_taken(
+        &self,
+        instruction: &Instruction,
+        untaken: &[bool],
+    ) -> Result<Value, RuntimeError> {
+        let (named, unnamed): (Vec<_>, Vec<_>) = instruction
+            .inputs
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !untaken.get(*index).copied().unwrap_or_default())
+            .map(|(_, binding)| binding)
+            .partition(|binding| binding.target_port.is_some());
+
+        let unnamed = match unnamed.as_slice() {
+            [] => Value::Null,
+            [single] => self.value_of(single),
+            many => Value::Array(many.iter().map(|b| self.value_of(b)).collect()),
+        };
+
+        if named.is_empty() {
+            return Ok(unnamed);
+        }
+
+        let mut fields = match unnamed {
+            Value::Object(fields) => fields,
+            Value::Null => BTreeMap::new(),
+            other => {
+                return Err(RuntimeError::at(
+                    &instruction.node_id,
+                    RuntimeErrorKind::InvalidInput {
+                        found: other.value_type(),
+                    },
+                ));
+            }
+        };
+
+        for binding in named {
+            if let Some(port) = &binding.target_port {
+                fields.insert(port.clone(), self.value_of(binding));
+            }
+        }
+
+        Ok(Value::Object(fields))
+    }
+}
+
+/// Options applied to a single run.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RunOptions {
+    /// Maximum number of nodes executing at the same time. `None` means unbounded.
+    pub max_concurrency: Option<usize>,
+    /// Maximum nesting of function graph calls. `None` uses [`DEFAULT_MAX_CALL_DEPTH`].
+    pub max_call_depth: Option<usize>,
+    /// How long a single node may run when it doesn't set `timeoutMs` itself.
+    /// `None` means no limit.
+    pub node_timeout_ms: Option<u64>,
+}
+
+#[derive(Clone)]
+pub struct Interpreter {
+    program: Arc<Program>,
+    host: Arc<dyn FunctionHost>,
+    events: Option<Arc<dyn EventSink>>,
+    graphs: Option<Arc<dyn GraphLoader>>,
+    observer: Option<Arc<dyn RunObserver>>,
+    debugger: Option<Arc<Debugger>>,
+    cancellation: Option<Arc<Cancellation>>,
+    replay: Option<Arc<Replay>>,
+    cache: Option<Arc<dyn OutputCache>>,
+    scripts: Arc<ScriptEngine>,
+    secrets: Arc<Secrets>,
+    environment: Option<Arc<Environment>>,
+    /// The variables of the run or function graph call in progress, replaced
+    /// with a fresh scope whenever the program starts executing.
+    variables: Arc<VariableScope>,
+    call_stack: Arc<Vec<CallFrame>>,
+    event_depth: usize,
+    options: RunOptions,
+}
+
+impl Interpreter {
+    pub fn new(program: Arc<Program>, host: Arc<dyn FunctionHost>) -> Self {
+        Self {
+            program,
+            host,
+            events: None,
+            graphs: None,
+            observer: None,
+            debugger: None,
+            cancellation: None,
+            replay: None,
+            cache: None,
+            scripts: ScriptEngine::shared(),
+            secrets: Arc::default(),
+            environment: None,
+            variables: Arc::default(),
+            call_stack: Arc::default(),
+            event_depth: 0,
+            options: RunOptions::default(),
+        }
+    }
+
+    /// Resolves the graphs called by `fnTrigger` nodes through `graphs`.
+    /// Without a loader, calling a function graph fails.
+    pub fn with_graphs(mut self, graphs: Arc<dyn GraphLoader>) -> Self {
+        self.graphs = Some(graphs);
+        self
+    }
+
+    /// Sends the events fired by `eventTrigger` nodes to `events`.
+    /// Without a sink, triggers only pass their payload on.
+    pub fn with_events(mut self, events: Arc<dyn EventSink>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Counts the events fired in a row to start the runs of this interpreter,
+    /// each from a graph started by the previous one. `eventTrigger` nodes
+    /// fail once it reaches [`MAX_EVENT_DEPTH`].
+    pub fn with_event_depth(mut self, depth: usize) -> Self {
+        self.event_depth = depth;
+        self
+    }
+
+    /// Reports the progress of every run, including the function graphs it
+    /// calls, to `observer`.
+    pub fn with_observer(mut self, observer: Arc<dyn RunObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Lets `debugger` suspend the run before any node, including the nodes
+    /// of called function graphs.
+    pub fn with_debugger(mut self, debugger: Arc<Debugger>) -> Self {
+        self.debugger = Some(debugger);
+        self
+    }
+
+    /// Stops the run, including the function graphs it calls, once
+    /// `cancellation` is cancelled.
+    pub fn with_cancellation(mut self, cancellation: Arc<Cancellation>) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Answers every plugin call from `replay` instead of calling the plugin.
+    pub fn with_replay(mut self, replay: Arc<Replay>) -> Self {
+        self.replay = Some(replay);
+        self
+    }
+
+    /// Reuses the outputs `cache` holds for pure functions called with the same arguments.
+    /// Calls given a secret always run, and their outputs aren't stored.
+    pub fn with_cache(mut self, cache: Arc<dyn OutputCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Runs the scripts of `scriptNode`s on `scripts` instead of the shared engine.
+    pub fn with_scripts(mut self, scripts: Arc<ScriptEngine>) -> Self {
+        self.scripts = scripts;
+        self
+    }
+
+    /// Resolves the `${secret.NAME}` references in node arguments from
+    /// `secrets`, and keeps the secrets out of everything the run reports and
+    /// returns. Without a store, nodes referencing a secret fail.
+    pub fn with_secrets(mut self, secrets: Arc<dyn SecretStore>) -> Self {
+        self.secrets = Arc::new(Secrets::new(secrets));
+        self
+    }
+
+    /// Resolves the `${env.NAME}` references in node arguments from
+    /// `environment`. Variables may hold secret references themselves, which
+    /// are resolved next. Without an environment, references are passed on as
+    /// they are.
+    pub fn with_environment(mut self, environment: Arc<Environment>) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    pub fn with_options(mut self, options: RunOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Runs the program from its start and function entry points, passing
+    /// `input` to each of them. Event listeners are left alone, see [`Self::run_event`].
+    pub async fn run(&self, input: Value) -> Result<RunOutput, RuntimeError> {
+        let started = Instant::now();
+        let roots = self.entries(|kind| matches!(kind, EntryKind::Start | EntryKind::Function));
+        self.finish(started, self.execute(roots, input).await)
+    }
+
+    /// Runs the program from every `eventListener` triggered by `event`, with
+    /// `payload` as their input.
+    pub async fn run_event(&self, event: &str, payload: Value) -> Result<RunOutput, RuntimeError> {
+        let started = Instant::now();
+        let roots = self.entries(|kind| matches!(kind, EntryKind::Event { name } if name == event));
+        self.finish(started, self.execute(roots, payload).await)
+    }
+
+    /// Reports the end of a run and returns its result, with the secrets it
+    /// resolved redacted like everything else the run reports.
+    fn finish(
+        &self,
+        started: Instant,
+        result: Result<RunOutput, RuntimeError>,
+    ) -> Result<RunOutput, RuntimeError> {
+        let result = result
+            .map(|output| RunOutput {
+                outputs: output
+                    .outputs
+                    .into_iter()
+                    .map(|(node_id, value)| (node_id, self.secrets.redact(value)))
+                    .collect(),
+            })
+            .map_err(|error| self.secrets.redact_error(error));
+
+        self.notify(|| ExecutionEvent::RunFinished {
+            error: result.as_ref().err().cloned(),
+            duration_ms: elapsed_ms(started),
+        });
+        result
+    }
+
+    /// Only builds the event when someone is listening, as node outputs can be large.
+    /// Secrets resolved by the run are redacted from it.
+    fn notify(&self, event: impl FnOnce() -> ExecutionEvent) {
+        if let Some(observer) = &self.observer {
+            observer.notify(self.secrets.redact_event(event()));
+        }
+    }
+
+    fn entries(&self, filter: impl Fn(&EntryKind) -> bool) -> VecDeque<InstructionId> {
+        self.program
+            .entry_points
+            .iter()
+            .copied()
+            .filter(
+                |id| matches!(&self.program.instructions[*id].op, Op::Entry(kind) if filter(kind)),
+            )
+            .collect()
+    }
+
+    /// Every instruction is started as soon as all of its inputs are available,
+    /// so independent branches execute concurrently on the tokio runtime.
+    /// Instructions that can't be reached from `ready` are skipped.
+    ///
+    /// Edges leaving a port an `ifNode` or `switchNode` didn't take, and edges
+    /// leaving skipped instructions, aren't taken. An instruction none of
+    /// whose edges are taken is skipped; one with some taken edges runs with
+    /// the values of those only, so branches can join again.
+    ///
+    /// Every execution starts with the variables of the program at their defaults.
+    async fn execute(
+        &self,
+        mut ready: VecDeque<InstructionId>,
+        input: Value,
+    ) -> Result<RunOutput, RuntimeError> {
+        let scoped = Interpreter {
+            variables: Arc::new(VariableScope::new(&self.program.variables)),
+            ..self.clone()
+        };
+        let instructions = &self.program.instructions;
+        let mut stack = ValueStack::with_capacity(instructions.len());
+        let mut pending_inputs = instructions
+            .iter()
+            .map(|i| i.inputs.len())
+            .collect::<Vec<_>>();
+        // Inputs whose edge wasn't taken, by instruction and binding index.
+        let mut untaken = instructions
+            .iter()
+            .map(|i| vec![false; i.inputs.len()])
+            .collect::<Vec<_>>();
+
+        let permits = self
+            .options
+            .max_concurrency
+            .unwrap_or(Semaphore::MAX_PERMITS)
+            .clamp(1, Semaphore::MAX_PERMITS);
+        let semaphore = Arc::new(Semaphore::new(permits));
+
+        let mut running = JoinSet::new();
+
+        loop {
+            while let Some(id) = ready.pop_front() {
+                let instruction = &instructions[id];
+                if !instruction.inputs.is_empty() && untaken[id].iter().all(|untaken| *untaken) {
+                    self.notify(|| ExecutionEvent::NodeSkipped {
+                        node_id: instruction.node_id.clone(),
+                        call_stack: self.call_stack.to_vec(),
+                    });
+                    release(
+                        id,
+                        instructions,
+                        &stack,
+                        &mut untaken,
+                        &mut pending_inputs,
+                        &mut ready,
+                    );
+                    continue;
+                }
+
+                let incoming = match instruction.op {
+                    Op::Entry(_) => Ok(input.clone()),
+                    _ => stack.gather_taken(instruction, &untaken[id]),
+                };
+                let incoming = match incoming {
+                    Ok(incoming) => incoming,
+                    Err(error) => {
+                        self.notify(|| ExecutionEvent::NodeFailed {
+                            node_id: instruction.node_id.clone(),
+                            call_stack: self.call_stack.to_vec(),
+                            error: error.clone(),
+                            duration_ms: 0,
+                        });
+                        // Dropping `running` aborts every branch still in flight.
+                        return Err(error);
+                    }
+                };
+
+                let interpreter = scoped.clone();
+                let semaphore = semaphore.clone();
+
+                running.spawn(async move {
+                    (id, interpreter.observed_step(id, incoming, semaphore).await)
+                });
+            }
+
+            let Some(finished) = running.join_next().await else {
+                break;
+            };
+
+            let (id, result) = finished.map_err(|e| {
+                RuntimeError::new(RuntimeErrorKind::TaskFailed {
+                    message: e.to_string(),
+                })
+            })?;
+
+            match result {
+                Ok(value) => stack.set(id, value),
+                Err(error) => {
+                    if error.kind == RuntimeErrorKind::Cancelled {
+                        // Every other branch stops as well; let them report it.
+                        while running.join_next().await.is_some() {}
+                    }
+                    // Dropping `running` aborts every branch still in flight.
+                    return Err(error);
+                }
+            }
+
+            release(
+                id,
+                instructions,
+                &stack,
+                &mut untaken,
+                &mut pending_inputs,
+                &mut ready,
+            );
+
+            if let Some(debugger) = &self.debugger {
+                for (next, index) in taken_edges(id, instructions, &untaken) {
+                    let binding = &instructions[next].inputs[index];
+                    let value = self.secrets.redact(stack.value_of(binding));
+                    debugger.record_edge(&binding.edge_id, value);
+                }
+            }
+        }
+
+        // Collected by node id, so the result doesn't depend on which branch finished first.
+        let outputs = instructions
+            .iter()
+            .enumerate()
+            .filter(|(_, instruction)| instruction.outputs.is_empty())
+            .filter_map(|(id, instruction)| {
+                stack
+                    .get(id)
+                    .map(|value| (instruction.node_id.clone(), value.clone()))
+            })
+            .collect();
+
+        Ok(RunOutput { outputs })
+    }
+
+    /// Runs the instruction `id` once a permit of `semaphore` is available
+    /// and the debugger lets it, reporting its progress to the observer.
+    async fn observed_step(
+        &self,
+        id: InstructionId,
+        incoming: Value,
+        semaphore: Arc<Semaphore>,
+    ) -> Result<Value, RuntimeError> {
+        let instruction = &self.program.instructions[id];
+        let node_id = &instruction.node_id;
+        let timeout_ms = instruction.timeout_ms.or(self.options.node_timeout_ms);
+
+        let mut started = None;
+
+        let work = async {
+            let _permit = semaphore.acquire_owned().await;
+
+            if let Some(debugger) = &self.debugger {
+                debugger
+                    .checkpoint(node_id, &self.call_stack, |suspension| {
+                        self.notify(|| ExecutionEvent::NodePaused {
+                            node_id: node_id.clone(),
+                            call_stack: suspension.call_stack.clone(),
+                            reason: suspension.reason,
+                        })
+                    })
+                    .await;
+            }
+
+            self.notify(|| ExecutionEvent::NodeStarted {
+                node_id: node_id.clone(),
+                call_stack: self.call_stack.to_vec(),
+                input: incoming.clone(),
+            });
+
+            // Time spent suspended by the debugger doesn't count towards the timeout.
+            started = Some(Instant::now());
+
+            let mut attempt = 1;
+            loop {
+                let result = match timeout_ms {
+                    Some(timeout_ms) => tokio::time::timeout(
+                        Duration::from_millis(timeout_ms),
+                        self.step(id, incoming.clone()),
+                    )
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(RuntimeError::at(
+                            node_id,
+                            RuntimeErrorKind::TimedOut { timeout_ms },
+                        ))
+                    }),
+                    None => self.step(id, incoming.clone()).await,
+                };
+
+                let (Err(error), Some(retry)) = (&result, &instruction.retry) else {
+                    return result;
+                };
+                if !retry.retries(attempt, error) {
+                    return result;
+                }
+
+                let delay = retry.delay_after(attempt);
+                self.notify(|| ExecutionEvent::NodeRetrying {
+                    node_id: node_id.clone(),
+                    call_stack: self.call_stack.to_vec(),
+                    attempt,
+                    error: error.clone(),
+                    delay_ms: delay.as_millis().try_into().unwrap_or(u64::MAX),
+                });
+
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        };
+
+        let result = match &self.cancellation {
+            Some(cancellation) => tokio::select! {
+                biased;
+                _ = cancellation.cancelled() => {
+                    Err(RuntimeError::at(node_id, RuntimeErrorKind::Cancelled))
+                }
+                result = work => result,
+            },
+            None => work.await,
+        };
+
+        self.notify(|| {
+            let node_id = node_id.clone();
+            let call_stack = self.call_stack.to_vec();
+            let duration_ms = started.map(elapsed_ms).unwrap_or_default();
+
+            match &result {
+                Ok(output) => ExecutionEvent::NodeFinished {
+                    node_id,
+                    call_stack,
+                    output: output.clone(),
+                    duration_ms,
+                },
+                // Also when a function graph the node calls was cancelled first.
+                Err(error) if error.kind == RuntimeErrorKind::Cancelled => {
+                    ExecutionEvent::NodeCancelled {
+                        node_id,
+                        call_stack,
+                        started: started.is_some(),
+                        duration_ms,
+                    }
+                }
+                // Raised by the node itself, not a function graph it calls;
+                // also when replaying a node recorded as timed out.
+                Err(RuntimeError {
+                    node_id: Some(failed),
+                    kind: RuntimeErrorKind::TimedOut { timeout_ms },
+                    call_stack: failed_in,
+                }) if *failed == node_id && failed_in.is_empty() => ExecutionEvent::NodeTimedOut {
+                    node_id,
+                    call_stack,
+                    timeout_ms: *timeout_ms,
+                    duration_ms,
+                },
+                Err(error) => ExecutionEvent::NodeFailed {
+                    node_id,
+                    call_stack,
+                    error: error.clone(),
+                    duration_ms,
+                },
+            }
+        });
+
+        result
+    }
+
+    async fn step(&self, id: InstructionId, incoming: Value) -> Result<Value, RuntimeError> {
+        let program = self.program.as_ref();
+        let instruction = &program.instructions[id];
+
+        match &instruction.op {
+            // Function graphs check their arguments against the schema of their entry.
+            Op::Entry(_) => apply_input_ports(instruction, incoming),
+            Op::Call {
+                function,
+                arguments,
+                pure,
+            } => {
+                let function_ref = program.function(*function).ok_or_else(|| {
+                    RuntimeError::at(&instruction.node_id, RuntimeErrorKind::InvalidProgram)
+                })?;
+
+                let constant = self.constant(instruction, *arguments)?;
+                let arguments =
+                    apply_input_ports(instruction, merge_arguments(constant.as_ref(), incoming))?;
+
+                if let Some(replay) = &self.replay {
+                    return replay.answer(&self.call_stack, &instruction.node_id);
+                }
+
+                // Outputs of calls given secrets stay out of caches, which
+                // would keep them on disk in plain text.
+                let memo = match &self.cache {
+                    Some(cache) if *pure && !self.secrets.appear_in(&arguments) => self
+                        .host
+                        .plugin_hash(&function_ref.plugin)
+                        .map(|hash| (cache.clone(), MemoKey::new(function_ref, &hash, &arguments))),
+                    _ => None,
+                };
+                if let Some((cache, key)) = memo.clone() {
+                    // Caches do file I/O, kept off the threads driving the other nodes.
+                    let cached = tokio::task::spawn_blocking(move || cache.get(&key)).await;
+                    if let Ok(Some(output)) = cached {
+                        return Ok(output);
+                    }
+                }
+
+                let output = self
+                    .host
+                    .call(function_ref, arguments)
+                    .await
+                    .map_err(|message| {
+                        RuntimeError::at(
+                            &instruction.node_id,
+                            RuntimeErrorKind::FunctionFailed { message },
+                        )
+                    })?;
+
+                if let Some((cache, key)) = memo {
+                    let stored = output.clone();
+                    // Caching is best effort, a failed store only costs a call next time.
+                    let _ = tokio::task::spawn_blocking(move || cache.put(&key, &stored)).await;
+                }
+                Ok(output)
+            }
+            Op::Script { script, arguments } => {
+                let script = program.script(*script).cloned().ok_or_else(|| {
+                    RuntimeError::at(&instruction.node_id, RuntimeErrorKind::InvalidProgram)
+                })?;
+
+                let constant = self.constant(instruction, *arguments)?;
+                let arguments =
+                    apply_input_ports(instruction, merge_arguments(constant.as_ref(), incoming))?;
+
+                // Scripts are plain CPU work, kept off the threads driving the other nodes.
+                // Replays don't reach plugins, not even through scripts.
+                let host = self.replay.is_none().then(|| self.host.clone());
+                let engine = self.scripts.clone();
+                let name = script.name.clone();
+                // Dropping this future, as a timeout or a cancelled run does,
+                // stops the script instead of leaving it running on its own.
+                let cancellation = Arc::new(Cancellation::new());
+                let _stop = cancellation.cancel_on_drop();
+                tokio::task::spawn_blocking(move || {
+                    engine.run_until_cancelled(&script, arguments, host, Some(cancellation))
+                })
+                .await
+                .map_err(|e| {
+                    RuntimeError::at(
+                        &instruction.node_id,
+                        RuntimeErrorKind::TaskFailed {
+                            message: e.to_string(),
+                        },
+                    )
+                })?
+                .map_err(|error| {
+                    RuntimeError::at(
+                        &instruction.node_id,
+                        RuntimeErrorKind::ScriptFailed {
+                            script: name,
+                            message: error.message,
+                            line: error.line,
+                            column: error.column,
+                        },
+                    )
+                })
+            }
+            Op::If => {
+                let (condition, payload) = branch_input(incoming, CONDITION_PORT);
+                let condition = expect_bool(instruction, CONDITION_PORT, condition)?;
+                let port = if condition { THEN_PORT } else { ELSE_PORT };
+                Ok(Value::Object(BTreeMap::from([(port.to_string(), payload)])))
+            }
+            Op::Switch { cases } => {
+                let (value, payload) = branch_input(incoming, VALUE_PORT);
+                let port = cases
+                    .iter()
+                    .find(|case| case.value == value)
+                    .map_or(DEFAULT_PORT, |case| case.port.as_str());
+                Ok(Value::Object(BTreeMap::from([(port.to_string(), payload)])))
+            }
+            Op::Gate(kind) => evaluate_gate(instruction, *kind, incoming),
+            Op::Emit { event } => {
+                if let Some(events) = &self.events {
+                    if self.event_depth >= MAX_EVENT_DEPTH {
+                        return Err(RuntimeError::at(
+                            &instruction.node_id,
+                            RuntimeErrorKind::EventDepthLimit {
+                                event: event.clone(),
+                                limit: MAX_EVENT_DEPTH,
+                            },
+                        ));
+                    }
+                    events.emit(event, incoming.clone(), self.event_depth);
+                }
+                Ok(incoming)
+            }
+            Op::CallGraph { graph, arguments } => {
+                let constant = self.constant(instruction, *arguments)?;
+                let arguments =
+                    apply_input_ports(instruction, merge_arguments(constant.as_ref(), incoming))?;
+
+                self.call_graph(instruction, graph, None, arguments).await
+            }
+            Op::GetVariable { variable } => self.variables.get(*variable).ok_or_else(|| {
+                RuntimeError::at(&instruction.node_id, RuntimeErrorKind::InvalidProgram)
+            }),
+            Op::SetVariable { variable } => {
+                let declared = program.variable(*variable).ok_or_else(|| {
+                    RuntimeError::at(&instruction.node_id, RuntimeErrorKind::InvalidProgram)
+                })?;
+
+                let value = match incoming.get(VALUE_PORT).cloned() {
+                    Some(value) => value,
+                    None => incoming,
+                };
+                let value = value.coerce(declared.value_type).map_err(|e| {
+                    RuntimeError::at(
+                        &instruction.node_id,
+                        RuntimeErrorKind::TypeMismatch {
+                            port: VALUE_PORT.to_string(),
+                            message: e.to_string(),
+                        },
+                    )
+                })?;
+
+                self.variables.set(*variable, value.clone());
+                Ok(value)
+            }
+            Op::Iterate {
+                kind,
+                graph,
+                parallelism,
+                on_error,
+                initial,
+            } => {
+                let items = match incoming.get(ITEMS_PORT).cloned() {
+                    Some(items) => items,
+                    None => incoming,
+                };
+                let Value::Array(items) = items else {
+                    return Err(RuntimeError::at(
+                        &instruction.node_id,
+                        RuntimeErrorKind::TypeMismatch {
+                            port: ITEMS_PORT.to_string(),
+                            message: format!("expected an array, got {}", items.value_type()),
+                        },
+                    ));
+                };
+
+                let (output, failures) = match kind {
+                    IterationKind::Reduce => {
+                        let initial = self.constant(instruction, *initial)?;
+                        self.reduce(instruction, graph, items, initial, *on_error)
+                            .await?
+                    }
+                    _ => {
+                        self.map_items(id, *kind, graph, items, *parallelism, *on_error)
+                            .await?
+                    }
+                };
+
+                Ok(match on_error {
+                    ItemErrorPolicy::Fail => output,
+                    ItemErrorPolicy::Collect => Value::Object(BTreeMap::from([
+                        ("output".to_string(), output),
+                        ("failures".to_string(), Value::Array(failures)),
+                    ])),
+                })
+            }
+        }
+    }
+
+    /// Runs the body `graph` of the map or filter instruction `id` for every
+    /// item, at most `parallelism` at a time, and produces the mapped or kept
+    /// items in their original order along with the failures `on_error` collected.
+    async fn map_items(
+        &self,
+        id: InstructionId,
+        kind: IterationKind,
+        graph: &str,
+        items: Vec<Value>,
+        parallelism: Option<u32>,
+        on_error: ItemErrorPolicy,
+    ) -> Result<(Value, Vec<Value>), RuntimeError> {
+        let instruction = &self.program.instructions[id];
+        let permits = parallelism
+            .map_or(Semaphore::MAX_PERMITS, |parallelism| parallelism as usize)
+            .clamp(1, Semaphore::MAX_PERMITS);
+        let semaphore = Arc::new(Semaphore::new(permits));
+
+        let mut running = JoinSet::new();
+        for (index, item) in items.iter().cloned().enumerate() {
+            let interpreter = self.clone();
+            let semaphore = semaphore.clone();
+            let graph = graph.to_string();
+
+            running.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let instruction = &interpreter.program.instructions[id];
+                let arguments = item_arguments(index, item, None);
+                let result = interpreter
+                    .call_graph(instruction, &graph, Some(index), arguments)
+                    .await;
+                (index, result)
+            });
+        }
+
+        let mut outputs = vec![None; items.len()];
+        let mut failures = vec![];
+        while let Some(finished) = running.join_next().await {
+            let (index, result) = finished.map_err(|e| {
+                RuntimeError::at(
+                    &instruction.node_id,
+                    RuntimeErrorKind::TaskFailed {
+                        message: e.to_string(),
+                    },
+                )
+            })?;
+
+            let result = result.and_then(|output| match kind {
+                IterationKind::Filter => {
+                    expect_bool(instruction, KEEP_PORT, output).map(Value::Bool)
+                }
+                _ => Ok(output),
+            });
+
+            match result {
+                Ok(output) => outputs[index] = Some(output),
+                Err(error) if collects(on_error, &error) => failures.push((index, error)),
+                // Dropping `running` stops the other items.
+                Err(error) => return Err(error),
+            }
+        }
+
+        let output = match kind {
+            IterationKind::Filter => items
+                .into_iter()
+                .zip(outputs)
+                .filter(|(_, keep)| *keep == Some(Value::Bool(true)))
+                .map(|(item, _)| item)
+                .collect(),
+            _ => outputs.into_iter().flatten().collect(),
+        };
+
+        failures.sort_by_key(|(index, _)| *index);
+        Ok((Value::Array(output), failure_values(failures)))
+    }
+
+    /// Runs the body `graph` of `instruction` for one item after the other,
+    /// each time with the output for the previous item as `accumulator`.
+    async fn reduce(
+        &self,
+        instruction: &Instruction,
+        graph: &str,
+        items: Vec<Value>,
+        initial: Option<Value>,
+        on_error: ItemErrorPolicy,
+    ) -> Result<(Value, Vec<Value>), RuntimeError> {
+        let mut accumulator = initial.unwrap_or_default();
+        let mut failures = vec![];
+
+        for (index, item) in items.into_iter().enumerate() {
+            let arguments = item_arguments(index, item, Some(accumulator.clone()));
+            match self
+                .call_graph(instruction, graph, Some(index), arguments)
+                .await
+            {
+                Ok(output) => accumulator = output,
+                Err(error) if collects(on_error, &error) => failures.push((index, error)),
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok((accumulator, failure_values(failures)))
+    }
+
+    /// The constant `id` of `instruction`, with the environment variables and
+    /// secrets it references resolved.
+    fn constant(
+        &self,
+        instruction: &Instruction,
+        id: Option<ConstantId>,
+    ) -> Result<Option<Value>, RuntimeError> {
+        let Some(id) = id else {
+            return Ok(None);
+        };
+
+        let constant = self.program.constant(id).ok_or_else(|| {
+            RuntimeError::at(&instruction.node_id, RuntimeErrorKind::InvalidProgram)
+        })?;
+
+        let constant = match &self.environment {
+            Some(environment) => environment.resolve(constant).map_err(|name| {
+                RuntimeError::at(
+                    &instruction.node_id,
+                    RuntimeErrorKind::UnknownEnvironmentVariable { name },
+                )
+            })?,
+            None => constant.clone(),
+        };
+
+        self.secrets.resolve(&constant).map(Some).map_err(|name| {
+            RuntimeError::at(
+                &instruction.node_id,
+                RuntimeErrorKind::UnknownSecret { name },
+            )
+        })
+    }
+
+    /// Runs the function graph `graph` from its `fnEntry` with `arguments`, one
+    /// level deeper on the call stack.
+    ///
+    /// Errors raised inside the callee keep the call stack at the point they
+    /// were raised.
+    ///
+    /// Boxed, as the callee runs the same future types as the caller.
+    fn call_graph<'a>(
+        &'a self,
+        instruction: &'a Instruction,
+        graph: &'a str,
+        item: Option<usize>,
+        arguments: Value,
+    ) -> BoxFuture<'a, Result<Value, RuntimeError>> {
+        Box::pin(async move {
+            let mut call_stack = self.call_stack.as_ref().clone();
+            call_stack.push(CallFrame {
+                graph: graph.to_string(),
+                node_id: instruction.node_id.clone(),
+                item,
+            });
+
+            let limit = self
+                .options
+                .max_call_depth
+                .unwrap_or(DEFAULT_MAX_CALL_DEPTH);
+            if call_stack.len() > limit {
+                let mut error = RuntimeError::at(
+                    &instruction.node_id,
+                    RuntimeErrorKind::RecursionLimit { limit },
+                );
+                error.call_stack = call_stack;
+                return Err(error);
+            }
+
+            let unknown_graph = |message: String| {
+                RuntimeError::at(
+                    &instruction.node_id,
+                    RuntimeErrorKind::UnknownGraph {
+                        graph: graph.to_string(),
+                        message,
+                    },
+                )
+            };
+
+            let loader = self.graphs.as_ref().ok_or_else(|| {
+                unknown_graph("function graphs aren't available in this run".into())
+            })?;
+            let program = loader.load(graph).await.map_err(unknown_graph)?;
+
+            let callee = Interpreter {
+                program,
+                call_stack: Arc::new(call_stack.clone()),
+                ..self.clone()
+            };
+
+            let roots = callee.entries(|kind| matches!(kind, EntryKind::Function));
+            if roots.is_empty() {
+                return Err(unknown_graph("graph has no fnEntry node".into()));
+            }
+
+            let output = callee
+                .execute(roots, arguments)
+                .await
+                .map_err(|mut error| {
+                    if error.call_stack.is_empty() {
+                        error.call_stack = call_stack;
+                    }
+                    error
+                })?;
+
+            Ok(output.into_value())
+        })
+    }
+}
+
+/// Input port of an `ifNode` holding the condition.
+pub const CONDITION_PORT: &str = "condition";
+/// Input port of a `switchNode` holding the value compared to its cases, and
+/// of a `setVariable` holding the value stored.
+pub const VALUE_PORT: &str = "value";
+/// Input port of a branch node holding the value passed on. Without it, the
+/// whole input is passed on.
+pub const PAYLOAD_PORT: &str = "payload";
+
+/// Input port of an iteration node holding the array it iterates over.
+/// Without it, the whole input is iterated over.
+pub const ITEMS_PORT: &str = "items";
+/// Output of the function graph of a `filterNode`, the bool telling whether
+/// the item is kept.
+pub const KEEP_PORT: &str = "keep";
+
+/// The arguments the body of an iteration node is called with for one item.
+fn item_arguments(index: usize, item: Value, accumulator: Option<Value>) -> Value {
+    let mut arguments = BTreeMap::from([
+        ("item".to_string(), item),
+        ("index".to_string(), Value::from(index as i64)),
+    ]);
+    if let Some(accumulator) = accumulator {
+        arguments.insert("accumulator".to_string(), accumulator);
+    }
+    Value::Object(arguments)
+}
+
+/// Whether `on_error` lets an iteration go on after `error`. Cancelling the
+/// run always stops it.
+fn collects(on_error: ItemErrorPolicy, error: &RuntimeError) -> bool {
+    on_error == ItemErrorPolicy::Collect && error.kind != RuntimeErrorKind::Cancelled
+}
+
+fn failure_values(failures: Vec<(usize, RuntimeError)>) -> Vec<Value> {
+    failures
+        .into_iter()
+        .map(|(index, error)| {
+            Value::Object(BTreeMap::from([
+                ("index".to_string(), Value::from(index as i64)),
+                (
+                    "error".to_string(),
+                    serde_json::to_value(&error)
+                        .map(Value::from)
+                        .unwrap_or_default(),
+                ),
+            ]))
+        })
+        .collect()
+}
+
+/// Settles the inputs `id` feeds into, now that it finished or was skipped,
+/// and queues the instructions that have all of their inputs settled.
+fn release(
+    id: InstructionId,
+    instructions: &[Instruction],
+    stack: &ValueStack,
+    untaken: &mut [Vec<bool>],
+    pending_inputs: &mut [usize],
+    ready: &mut VecDeque<InstructionId>,
+) {
+    let output = stack.get(id);
+    let branch = instructions[id].op.is_branch();
+
+    let mut targets = instructions[id].outputs.clone();
+    targets.sort_unstable();
+    targets.dedup();
+
+    for next in targets {
+        for (index, binding) in instructions[next].inputs.iter().enumerate() {
+            if binding.source != id {
+                continue;
+            }
+
+            let taken = match (output, &binding.source_port) {
+                (None, _) => false,
+                (Some(output), Some(port)) if branch => output.get(port).is_some(),
+                (Some(_), None) if branch => false,
+                (Some(_), _) => true,
+            };
+            untaken[next][index] = !taken;
+            pending_inputs[next] -= 1;
+        }
+
+        if pending_inputs[next] == 0 {
+            ready.push_back(next);
+        }
+    }
+}
+
+/// The taken edges leaving `id`, as target instruction and binding index.
+fn taken_edges<'a>(
+    id: InstructionId,
+    instructions: &'a [Instruction],
+    untaken: &'a [Vec<bool>],
+) -> impl Iterator<Item = (InstructionId, usize)> + 'a {
+    let mut targets = instructions[id].outputs.clone();
+    targets.sort_unstable();
+    targets.dedup();
+
+    targets.into_iter().flat_map(move |next| {
+        instructions[next]
+            .inputs
+            .iter()
+            .enumerate()
+            .filter(move |(index, binding)| binding.source == id && !untaken[next][*index])
+            .map(move |(index, _)| (next, index))
+    })
+}
+
+/// Splits the input of a branch node into the value it branches on, found
+/// in `port` or being the whole input, and the payload it passes on.
+fn branch_input(incoming: Value, port: &str) -> (Value, Value) {
+    match incoming.get(port).cloned() {
+        Some(selector) => {
+            let payload = incoming.get(PAYLOAD_PORT).cloned().unwrap_or(incoming);
+            (selector, payload)
+        }
+        None => (incoming.clone(), incoming),
+    }
+}
+
+fn expect_bool(instruction: &Instruction, port: &str, value: Value) -> Result<bool, RuntimeError> {
+    value
+        .coerce(ValueType::Bool)
+        .map(|value| value.as_bool() == Some(true))
+        .map_err(|e| {
+            RuntimeError::at(
+                &instruction.node_id,
+                RuntimeErrorKind::TypeMismatch {
+                    port: port.to_string(),
+                    message: e.to_string(),
+                },
+            )
+        })
+}
+
+/// Combines every input of a gate, gathered by port or in edge order.
+fn evaluate_gate(
+    instruction: &Instruction,
+    kind: GateKind,
+    incoming: Value,
+) -> Result<Value, RuntimeError> {
+    let inputs = match incoming {
+        Value::Object(fields) => fields.into_iter().collect::<Vec<_>>(),
+        Value::Array(values) => values
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| (index.to_string(), value))
+            .collect(),
+        single => vec![("input".to_string(), single)],
+    };
+
+    let values = inputs
+        .into_iter()
+        .map(|(port, value)| expect_bool(instruction, &port, value))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let output = match (kind, values.as_slice()) {
+        (GateKind::And, values) => values.iter().all(|value| *value),
+        (GateKind::Or, values) => values.iter().any(|value| *value),
+        (GateKind::Not, [value]) => !value,
+        (GateKind::Not, values) => {
+            return Err(RuntimeError::at(
+                &instruction.node_id,
+                RuntimeErrorKind::TypeMismatch {
+                    port: "input".to_string(),
+                    message: format!("expected a single boolean, got {}", values.len()),
+                },
+            ));
+        }
+    };
+
+    Ok(Value::Bool(output))
+}
+
+/// Coerces every field of `value` bound to a declared input port into the
+/// port's type, and checks that required ports received a value.
+///
+/// A non-object value is bound to the only port of a node declaring exactly
+/// one, and rejected by a node declaring more.
+pub fn apply_input_ports(instruction: &Instruction, value: Value) -> Result<Value, RuntimeError> {
+    let ports = &instruction.input_ports;
+    if ports.is_empty() {
+        return Ok(value);
+    }
+
+    let mut fields = match value {
+        Value::Object(fields) => fields,
+        Value::Null => BTreeMap::new(),
+        other if ports.len() == 1 => BTreeMap::from([(ports[0].id.clone(), other)]),
+        other => {
+            return Err(RuntimeError::at(
+                &instruction.node_id,
+                RuntimeErrorKind::InvalidInput {
+                    found: other.value_type(),
+                },
+            ));
+        }
+    };
+
+    for port in ports {
+        match fields.remove(&port.id) {
+            Some(value) => {
+                let value = value.coerce(port.value_type).map_err(|e| {
+                    RuntimeError::at(
+                        &instruction.node_id,
+                        RuntimeErrorKind::TypeMismatch {
+                            port: port.id.clone(),
+                            message: e.to_string(),
+                        },
+                    )
+                })?;
+                fields.insert(port.id.clone(), value);
+            }
+            None if port.required => {
+                return Err(RuntimeError::at(
+                    &instruction.node_id,
+                    RuntimeErrorKind::MissingInput {
+                        port: port.id.clone(),
+                    },
+                ));
+            }
+            None => {}
+        }
+    }
+
+    Ok(Value::Object(fields))
+}
+
+/// Combines the constant `arguments` stored on a node with the value flowing into it.
+///
+/// Fields of an incoming object override constants with the same name. Any
+/// other incoming value is only used when the node has no constant arguments.
+pub fn merge_arguments(constant: Option<&Value>, incoming: Value) -> Value {
+    match (constant, incoming) {
+        (None, incoming) => incoming,
+        (Some(Value::Object(constant)), Value::Object(incoming)) => {
+            let mut merged = constant.clone();
+            merged.extend(incoming);
+            Value::Object(merged)
+        }
+        (Some(constant), _) => constant.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::{BTreeMap, HashMap},
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use flow_rt_shared::{
+        schemas::{FlowGraph, FlowGraphEdge, FlowGraphNode},
+        value::{Value, ValueType},
+    };
+    use serde_json::json;
+
+    use crate::{
+        cancellation::Cancellation,
+        debugger::{Debugger, SuspendReason, Suspension},
+        environment::Environment,
+        error::RuntimeErrorKind,
+        interpreter::{BoxFuture, CallFrame, FunctionHost, Interpreter, KEEP_PORT, RunOptions},
+        ir::{EntryKind, FunctionRef, Op, Program, Script},
+        memo::{MemoKey, OutputCache},
+        observer::ExecutionEvent,
+        script::{ScriptEngine, ScriptSandbox},
+        testing::{
+            Recorder, RecordingHost, compiled, edge, function_node, graph, instruction, node,
+            port_edge, variable,
+        },
+    };
+
+    #[derive(Default)]
+    struct TestHost {
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+        calls: AtomicUsize,
+    }
+
+    impl FunctionHost for TestHost {
+        fn call<'a>(
+            &'a self,
+            function: &'a FunctionRef,
+            arguments: Value,
+        ) -> BoxFuture<'a, Result<Value, String>> {
+            Box::pin(async move {
+                let calls = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+                let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.peak.fetch_max(in_flight, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                match function.function.as_str() {
+                    "echo" => Ok(arguments),
+                    "flaky" if calls > 2 => Ok(arguments),
+                    _ => Err(format!("{} failed", function.function)),
+                }
+            })
+        }
+
+        fn plugin_hash(&self, _plugin: &str) -> Option<String> {
+            Some("test-build".into())
+        }
+    }
+
+    fn call() -> Op {
+        Op::Call {
+            function: 0,
+            arguments: Some(0),
+            pure: false,
+        }
+    }
+
+    fn program(function: &str, branches: usize) -> Arc<Program> {
+        let mut instructions = vec![instruction(
+            "start",
+            Op::Entry(EntryKind::Start),
+            vec![],
+            (1..=branches).collect(),
+        )];
+
+        for branch in 1..=branches {
+            instructions.push(instruction(
+                &format!("call{branch}"),
+                call(),
+                vec![0],
+                vec![],
+            ));
+        }
+
+        Arc::new(Program {
+            instructions,
+            constants: vec![json!({ "method": "GET", "url": "default" }).into()],
+            functions: vec![FunctionRef {
+                plugin: "test".into(),
+                function: function.into(),
+            }],
+            entry_points: vec![0],
+            ..Default::default()
+        })
+    }
+
+    /// [`program`] with one branch, calling `function` with `arguments`.
+    fn with_arguments(function: &str, arguments: serde_json::Value) -> Arc<Program> {
+        let mut program = Arc::unwrap_or_clone(program(function, 1));
+        program.constants[0] = arguments.into();
+        Arc::new(program)
+    }
+
+    fn echo(id: &str) -> FlowGraphNode {
+        function_node(id, "test", "echo")
+    }
+
+    #[tokio::test]
+    async fn run_should_pass_values_along_edges() {
+        let output = Interpreter::new(program("echo", 1), Arc::new(TestHost::default()))
+            .run(json!({ "url": "https://example.com" }).into())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            output.outputs["call1"],
+            Value::from(json!({ "method": "GET", "url": "https://example.com" }))
+        );
+    }
+
+    #[tokio::test]
+    async fn run_should_report_failing_node() {
+        let error = Interpreter::new(program("explode", 1), Arc::new(TestHost::default()))
+            .run(Value::Null)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.node_id.as_deref(), Some("call1"));
+    }
+
+    #[tokio::test]
+    async fn run_should_execute_independent_branches_concurrently() {
+        let host = Arc::new(TestHost::default());
+        let output = Interpreter::new(program("echo", 3), host.clone())
+            .run(Value::Null)
+            .await
+            .unwrap();
+
+        assert_eq!(output.outputs.len(), 3);
+        assert_eq!(host.peak.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn run_should_respect_concurrency_limit() {
+        let host = Arc::new(TestHost::default());
+        Interpreter::new(program("echo", 3), host.clone())
+            .with_options(RunOptions {
+                max_concurrency: Some(1),
+                ..Default::default()
+            })
+            .run(Value::Null)
+            .await
+            .unwrap();
+
+        assert_eq!(host.peak.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn run_should_bind_and_coerce_ports() {
+        let mut program = Arc::unwrap_or_clone(program("echo", 1));
+        program.instructions[1].inputs[0].target_port = Some("timeout".into());
+        program.instructions[1].input_ports = vec![
+            serde_json::from_value(json!({ "id": "timeout", "type": "number" })).unwrap(),
+            serde_json::from_value(json!({ "id": "url", "type": "string", "required": true }))
+                .unwrap(),
+        ];
+
+        let output = Interpreter::new(Arc::new(program.clone()), Arc::new(TestHost::default()))
+            .run(Value::from("250"))
+            .await
+            .unwrap();
+        assert_eq!(
+            output.outputs["call1"].get("timeout"),
+            Some(&Value::from(250i64))
+        );
+
+        program.constants.clear();
+        program.instructions[1].op = Op::Call {
+            function: 0,
+            arguments: None,
+            pure: false,
+        };
+        let error = Interpreter::new(Arc::new(program.clone()), Arc::new(TestHost::default()))
+            .run(Value::from("250"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.kind,
+            RuntimeErrorKind::MissingInput { port: "url".into() }
+        );
+
+        program.instructions[1].inputs[0].target_port = None;
+        let error = Interpreter::new(Arc::new(program), Arc::new(TestHost::default()))
+            .run(Value::from("250"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.kind,
+            RuntimeErrorKind::InvalidInput {
+                found: ValueType::String
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn unnamed_inputs_gathered_with_named_ones_should_be_objects() {
+        let program = compiled(
+            &graph(
+                vec![
+                    node("start", "startNode", json!({})),
+                    function_node("call", "test", "echo"),
+                ],
+                vec![
+                    edge("start", "call"),
+                    FlowGraphEdge {
+                        id: "start->call.url".into(),
+                        target_handle: Some("url".into()),
+                        ..edge("start", "call")
+                    },
+                ],
+            ),
+            &[],
+        );
+        let interpreter = Interpreter::new(program, Arc::new(RecordingHost::default()));
+
+        let output = interpreter
+            .run(json!({ "method": "GET" }).into())
+            .await
+            .unwrap();
+        assert_eq!(
+            output.outputs["call"],
+            json!({ "method": "GET", "url": { "method": "GET" } }).into()
+        );
+
+        let error = interpreter.run(json!("GET").into()).await.unwrap_err();
+        assert_eq!(error.node_id.as_deref(), Some("call"));
+        assert_eq!(
+            error.kind,
+            RuntimeErrorKind::InvalidInput {
+                found: ValueType::String
+            }
+        );
+    }
+
+    /// `fnEntry -> op`.
+    fn function_graph(op: Op) -> Arc<Program> {
+        let mut program = Arc::unwrap_or_clone(program("echo", 1));
+        program.instructions[0].op = Op::Entry(EntryKind::Function);
+        program.instructions[1].op = op;
+        Arc::new(program)
+    }
+
+    fn call_graph(graph: &str) -> Op {
+        Op::CallGraph {
+            graph: graph.into(),
+            arguments: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn call_graph_should_check_arguments_and_return_outputs() {
+        let mut fetch = Arc::unwrap_or_clone(function_graph(call()));
+        fetch.instructions[0].input_ports =
+            vec![serde_json::from_value(json!({ "id": "url", "required": true })).unwrap()];
+        let graphs = Arc::new(BTreeMap::from([("fetch".to_string(), Arc::new(fetch))]));
+
+        let mut caller = Arc::unwrap_or_clone(program("echo", 1));
+        caller.instructions[1].op = call_graph("fetch");
+        let caller =
+            Interpreter::new(Arc::new(caller), Arc::new(TestHost::default())).with_graphs(graphs);
+
+        let output = caller.run(json!({ "url": "x" }).into()).await.unwrap();
+        assert_eq!(
+            output.outputs["call1"],
+            Value::from(json!({ "method": "GET", "url": "x" }))
+        );
+
+        let error = caller.run(Value::Null).await.unwrap_err();
+        assert_eq!(
+            error.kind,
+            RuntimeErrorKind::MissingInput { port: "url".into() }
+        );
+        assert_eq!(
+            error.call_stack,
+            vec![CallFrame {
+                item: None,
+                graph: "fetch".into(),
+                node_id: "call1".into()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn call_graph_should_stop_runaway_recursion() {
+        let graphs = Arc::new(BTreeMap::from([(
+            "forever".to_string(),
+            function_graph(call_graph("forever")),
+        )]));
+
+        let error = Interpreter::new(
+            function_graph(call_graph("forever")),
+            Arc::new(TestHost::default()),
+        )
+        .with_graphs(graphs)
+        .with_options(RunOptions {
+            max_call_depth: Some(8),
+            ..Default::default()
+        })
+        .run(Value::Null)
+        .await
+        .unwrap_err();
+
+        assert_eq!(error.kind, RuntimeErrorKind::RecursionLimit { limit: 8 });
+        assert_eq!(error.call_stack.len(), 9);
+    }
+
+    #[tokio::test]
+    async fn run_should_report_progress_to_observer() {
+        let recorder = Arc::new(Recorder::default());
+        let error = Interpreter::new(program("explode", 1), Arc::new(TestHost::default()))
+            .with_observer(recorder.clone())
+            .run(Value::Null)
+            .await
+            .unwrap_err();
+
+        let events = recorder.events.lock().unwrap();
+        let kinds = events
+            .iter()
+            .map(|event| match event {
+                ExecutionEvent::NodeStarted { node_id, .. } => format!("started {node_id}"),
+                ExecutionEvent::NodeFinished { node_id, .. } => format!("finished {node_id}"),
+                ExecutionEvent::NodeFailed { node_id, .. } => format!("failed {node_id}"),
+                ExecutionEvent::NodePaused { node_id, .. } => format!("paused {node_id}"),
+                ExecutionEvent::NodeRetrying { node_id, .. } => format!("retrying {node_id}"),
+                ExecutionEvent::NodeCancelled { node_id, .. } => format!("cancelled {node_id}"),
+                ExecutionEvent::NodeTimedOut { node_id, .. } => format!("timed out {node_id}"),
+                ExecutionEvent::NodeSkipped { node_id, .. } => format!("skipped {node_id}"),
+                ExecutionEvent::RunFinished { .. } => "run finished".to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            kinds,
+            [
+                "started start",
+                "finished start",
+                "started call1",
+                "failed call1",
+                "run finished"
+            ]
+        );
+        assert!(matches!(
+            events.last(),
+            Some(ExecutionEvent::RunFinished { error: Some(e), .. }) if *e == error
+        ));
+    }
+
+    async fn until_suspended_at(debugger: &Debugger, node_id: &str) -> Suspension {
+        for _ in 0..200 {
+            if let Some(suspension) = debugger
+                .snapshot()
+                .suspended
+                .into_iter()
+                .find(|s| s.node_id == node_id)
+            {
+                return suspension;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("{node_id} was never suspended");
+    }
+
+    #[tokio::test]
+    async fn debugger_should_stop_at_breakpoints_and_show_edge_values() {
+        let debugger = Arc::new(Debugger::new(["call1".to_string()]));
+        let interpreter = Interpreter::new(program("echo", 1), Arc::new(TestHost::default()))
+            .with_debugger(debugger.clone());
+        let run = tokio::spawn(async move { interpreter.run(Value::from("in")).await });
+
+        let suspension = until_suspended_at(&debugger, "call1").await;
+        assert_eq!(suspension.reason, SuspendReason::Breakpoint);
+        assert_eq!(
+            debugger.snapshot().edges.get("0->call1"),
+            Some(&Value::from("in"))
+        );
+        assert!(!run.is_finished());
+
+        debugger.step_over();
+        assert!(run.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn debugger_should_pause_and_step() {
+        let debugger = Arc::new(Debugger::default());
+        debugger.pause();
+
+        let interpreter = Interpreter::new(program("echo", 1), Arc::new(TestHost::default()))
+            .with_debugger(debugger.clone());
+        let run = tokio::spawn(async move { interpreter.run(Value::Null).await });
+
+        let paused = until_suspended_at(&debugger, "start").await;
+        assert_eq!(paused.reason, SuspendReason::Paused);
+
+        debugger.step_into();
+        let stepped = until_suspended_at(&debugger, "call1").await;
+        assert_eq!(stepped.reason, SuspendReason::Step);
+
+        debugger.resume();
+        assert!(run.await.unwrap().is_ok());
+        assert!(!debugger.is_suspended());
+    }
+
+    #[tokio::test]
+    async fn run_should_stop_nodes_running_past_their_timeout() {
+        let recorder = Arc::new(Recorder::default());
+        let error = Interpreter::new(program("echo", 1), Arc::new(TestHost::default()))
+            .with_observer(recorder.clone())
+            .with_options(RunOptions {
+                node_timeout_ms: Some(1),
+                ..Default::default()
+            })
+            .run(Value::Null)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.node_id.as_deref(), Some("call1"));
+        assert_eq!(error.kind, RuntimeErrorKind::TimedOut { timeout_ms: 1 });
+        assert!(recorder.events.lock().unwrap().iter().any(|event| matches!(
+            event,
+            ExecutionEvent::NodeTimedOut { node_id, timeout_ms: 1, .. } if node_id == "call1"
+        )));
+    }
+
+    #[tokio::test]
+    async fn cancel_should_stop_pending_nodes() {
+        let debugger = Arc::new(Debugger::new(["call1".to_string()]));
+        let cancellation = Arc::new(Cancellation::new());
+        let recorder = Arc::new(Recorder::default());
+
+        let interpreter = Interpreter::new(program("echo", 1), Arc::new(TestHost::default()))
+            .with_debugger(debugger.clone())
+            .with_cancellation(cancellation.clone())
+            .with_observer(recorder.clone());
+        let run = tokio::spawn(async move { interpreter.run(Value::Null).await });
+
+        until_suspended_at(&debugger, "call1").await;
+        cancellation.cancel();
+
+        let error = run.await.unwrap().unwrap_err();
+        assert_eq!(error.kind, RuntimeErrorKind::Cancelled);
+        assert!(recorder.events.lock().unwrap().iter().any(|event| matches!(
+            event,
+            ExecutionEvent::NodeCancelled { node_id, started: false, .. } if node_id == "call1"
+        )));
+    }
+
+    #[tokio::test]
+    async fn run_should_retry_failing_nodes_with_their_policy() {
+        let mut program = Arc::unwrap_or_clone(program("flaky", 1));
+        program.instructions[1].retry = Some(
+            serde_json::from_value(json!({
+                "maxAttempts": 3,
+                "backoff": { "kind": "fixed", "delayMs": 1 },
+                "retryOn": ["functionFailed"]
+            }))
+            .unwrap(),
+        );
+
+        let host = Arc::new(TestHost::default());
+        let recorder = Arc::new(Recorder::default());
+        let output = Interpreter::new(Arc::new(program), host.clone())
+            .with_observer(recorder.clone())
+            .run(Value::Null)
+            .await
+            .unwrap();
+
+        assert!(output.outputs.contains_key("call1"));
+        assert_eq!(host.calls.load(Ordering::SeqCst), 3);
+
+        let attempts = recorder
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|event| match event {
+                ExecutionEvent::NodeRetrying { attempt, error, .. } => {
+                    Some((*attempt, error.kind.code()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(attempts, [(1, "functionFailed"), (2, "functionFailed")]);
+    }
+
+    #[derive(Default)]
+    struct MemoryCache {
+        outputs: Mutex<HashMap<MemoKey, Value>>,
+    }
+
+    impl OutputCache for MemoryCache {
+        fn get(&self, key: &MemoKey) -> Option<Value> {
+            self.outputs.lock().unwrap().get(key).cloned()
+        }
+
+        fn put(&self, key: &MemoKey, output: &Value) {
+            self.outputs
+                .lock()
+                .unwrap()
+                .insert(key.clone(), output.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn run_should_reuse_cached_outputs_of_pure_functions() {
+        let mut program = Arc::unwrap_or_clone(program("echo", 1));
+        program.instructions[1].op = Op::Call {
+            function: 0,
+            arguments: Some(0),
+            pure: true,
+        };
+        let program = Arc::new(program);
+
+        let host = Arc::new(TestHost::default());
+        let cache = Arc::new(MemoryCache::default());
+        for input in ["a", "a", "b"] {
+            Interpreter::new(program.clone(), host.clone())
+                .with_cache(cache.clone())
+                .run(json!({ "url": input }).into())
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(host.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.outputs.lock().unwrap().len(), 2);
+
+        // Impure functions are always called.
+        Interpreter::new(self::program("echo", 1), host.clone())
+            .with_cache(cache)
+            .run(json!({ "url": "a" }).into())
+            .await
+            .unwrap();
+        assert_eq!(host.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn run_should_execute_scripts_with_their_inputs() {
+        let mut program = Arc::unwrap_or_clone(program("echo", 1));
+        program.instructions[1].op = Op::Script {
+            script: 0,
+            arguments: Some(0),
+        };
+        program.scripts.push(Script {
+            name: "shout.rhai".into(),
+            source: "if url == \"\" { throw \"no url\" }\n`${method} ${url}`".into(),
+        });
+        let program = Arc::new(program);
+
+        let output = Interpreter::new(program.clone(), Arc::new(TestHost::default()))
+            .run(json!({ "url": "https://example.com" }).into())
+            .await
+            .unwrap();
+        assert_eq!(
+            output.outputs["call1"],
+            Value::from("GET https://example.com")
+        );
+
+        let error = Interpreter::new(program, Arc::new(TestHost::default()))
+            .run(json!({ "url": "" }).into())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.kind,
+            RuntimeErrorKind::ScriptFailed { line: Some(1), .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn scripts_should_call_plugins_their_sandbox_allows() {
+        let mut program = Arc::unwrap_or_clone(program("echo", 1));
+        program.instructions[1].op = Op::Script {
+            script: 0,
+            arguments: None,
+        };
+        program.scripts.push(Script {
+            name: "relay.rhai".into(),
+            source: r#"call_plugin("test", "echo", input) + "!""#.into(),
+        });
+
+        let scripts = ScriptEngine::new(ScriptSandbox {
+            allowed_functions: vec!["test::echo".into()],
+            ..Default::default()
+        });
+        let host = Arc::new(TestHost::default());
+        let output = Interpreter::new(Arc::new(program), host.clone())
+            .with_scripts(Arc::new(scripts))
+            .run(Value::from("hi"))
+            .await
+            .unwrap();
+
+        assert_eq!(output.outputs["call1"], Value::from("hi!"));
+        assert_eq!(host.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn scripts_should_stop_with_their_timed_out_node() {
+        let mut program = Arc::unwrap_or_clone(program("echo", 1));
+        program.instructions[1].op = Op::Script {
+            script: 0,
+            arguments: None,
+        };
+        program.instructions[1].timeout_ms = Some(20);
+        program.scripts.push(Script {
+            name: "spin.rhai".into(),
+            source: "loop {}".into(),
+        });
+
+        // Without limits of its own, the script only stops along with its
+        // node; otherwise the runtime would wait for it forever on shutdown.
+        let scripts = ScriptEngine::new(ScriptSandbox {
+            max_operations: 0,
+            timeout_ms: 0,
+            ..Default::default()
+        });
+        let error = Interpreter::new(Arc::new(program), Arc::new(TestHost::default()))
+            .with_scripts(Arc::new(scripts))
+            .run(Value::Null)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind, RuntimeErrorKind::TimedOut { timeout_ms: 20 });
+    }
+
+    #[tokio::test]
+    async fn branches_not_taken_should_be_skipped() {
+        let program = compiled(
+            &graph(
+                vec![
+                    node("start", "startNode", json!({})),
+                    node("if", "ifNode", json!({})),
+                    echo("yes"),
+                    echo("no"),
+                    echo("after-no"),
+                    echo("join"),
+                ],
+                vec![
+                    edge("start", "if"),
+                    port_edge("if", "then", "yes"),
+                    port_edge("if", "else", "no"),
+                    edge("no", "after-no"),
+                    edge("yes", "join"),
+                    edge("after-no", "join"),
+                ],
+            ),
+            &[],
+        );
+
+        let host = Arc::new(TestHost::default());
+        let recorder = Arc::new(Recorder::default());
+        let output = Interpreter::new(program, host.clone())
+            .with_observer(recorder.clone())
+            .run(json!({ "condition": true, "payload": "taken" }).into())
+            .await
+            .unwrap();
+
+        assert_eq!(output.outputs["join"], Value::from("taken"));
+        assert_eq!(host.calls.load(Ordering::SeqCst), 2);
+
+        let skipped = recorder
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|event| match event {
+                ExecutionEvent::NodeSkipped { node_id, .. } => Some(node_id.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(skipped, ["no", "after-no"]);
+    }
+
+    #[tokio::test]
+    async fn switch_should_take_the_matching_case_or_its_default() {
+        let program = compiled(
+            &graph(
+                vec![
+                    node("start", "startNode", json!({})),
+                    node("switch", "switchNode", json!({ "cases": ["red", 2] })),
+                    echo("red"),
+                    echo("two"),
+                    echo("other"),
+                ],
+                vec![
+                    edge("start", "switch"),
+                    port_edge("switch", "red", "red"),
+                    port_edge("switch", "2", "two"),
+                    port_edge("switch", "default", "other"),
+                ],
+            ),
+            &[],
+        );
+
+        let run = |input: serde_json::Value| {
+            let interpreter = Interpreter::new(program.clone(), Arc::new(TestHost::default()));
+            async move { interpreter.run(input.into()).await.unwrap().outputs }
+        };
+
+        let outputs = run(json!({ "value": 2, "payload": "x" })).await;
+        assert_eq!(outputs.keys().collect::<Vec<_>>(), ["two"]);
+        assert_eq!(outputs["two"], Value::from("x"));
+
+        let outputs = run(json!("blue")).await;
+        assert_eq!(outputs.keys().collect::<Vec<_>>(), ["other"]);
+        assert_eq!(outputs["other"], Value::from("blue"));
+    }
+
+    #[tokio::test]
+    async fn gates_should_combine_booleans() {
+        let program = compiled(
+            &graph(
+                vec![
+                    node("start", "startNode", json!({})),
+                    node("and", "andGate", json!({})),
+                    node("or", "orGate", json!({})),
+                    node("not", "notGate", json!({})),
+                ],
+                vec![
+                    edge("start", "and"),
+                    edge("start", "or"),
+                    edge("and", "not"),
+                ],
+            ),
+            &[],
+        );
+
+        let output = Interpreter::new(program.clone(), Arc::new(TestHost::default()))
+            .run(json!([true, false]).into())
+            .await
+            .unwrap();
+        assert_eq!(output.outputs["or"], Value::from(true));
+        assert_eq!(output.outputs["not"], Value::from(true));
+
+        let error = Interpreter::new(program, Arc::new(TestHost::default()))
+            .run(json!([true, "maybe"]).into())
+            .await
+            .unwrap_err();
+        assert!(matches!(error.kind, RuntimeErrorKind::TypeMismatch { .. }));
+    }
+
+    /// A function graph running `script` on its arguments, as the body of an iteration node.
+    fn body(script: &str) -> Arc<Program> {
+        compiled(
+            &graph(
+                vec![
+                    node("entry", "fnEntry", json!({})),
+                    node("script", "scriptNode", json!({ "scriptName": "body" })),
+                ],
+                vec![edge("entry", "script")],
+            ),
+            &[("body", script)],
+        )
+    }
+
+    /// A start node feeding each of `nodes`.
+    fn iterations(nodes: Vec<FlowGraphNode>) -> Arc<Program> {
+        let edges = nodes.iter().map(|node| edge("start", &node.id)).collect();
+        let mut all = vec![node("start", "startNode", json!({}))];
+        all.extend(nodes);
+        compiled(&graph(all, edges), &[])
+    }
+
+    #[tokio::test]
+    async fn iteration_nodes_should_map_filter_and_reduce() {
+        let graphs = Arc::new(BTreeMap::from([
+            ("double".to_string(), body("item * 2")),
+            ("even".to_string(), body("item % 2 == 0")),
+            ("sum".to_string(), body("accumulator + item")),
+        ]));
+        let program = iterations(vec![
+            node("map", "mapNode", json!({ "graphName": "double" })),
+            node(
+                "filter",
+                "filterNode",
+                json!({ "graphName": "even", "parallelism": 1 }),
+            ),
+            node(
+                "reduce",
+                "reduceNode",
+                json!({ "graphName": "sum", "initial": 10 }),
+            ),
+        ]);
+
+        let output = Interpreter::new(program, Arc::new(TestHost::default()))
+            .with_graphs(graphs)
+            .run(json!([1, 2, 3, 4]).into())
+            .await
+            .unwrap();
+
+        assert_eq!(output.outputs["map"], json!([2, 4, 6, 8]).into());
+        assert_eq!(output.outputs["filter"], json!([2, 4]).into());
+        assert_eq!(output.outputs["reduce"], Value::from(20));
+    }
+
+    #[tokio::test]
+    async fn iteration_nodes_should_fail_or_collect_item_errors() {
+        let graphs = Arc::new(BTreeMap::from([(
+            "fragile".to_string(),
+            body(r#"if item == 0 { throw "zero" } 10 / item"#),
+        )]));
+        let run = |on_error: &str| {
+            let program = iterations(vec![node(
+                "map",
+                "mapNode",
+                json!({ "graphName": "fragile", "onError": on_error }),
+            )]);
+            let interpreter = Interpreter::new(program, Arc::new(TestHost::default()))
+                .with_graphs(graphs.clone());
+            async move { interpreter.run(json!([1, 0, 5]).into()).await }
+        };
+
+        let output = run("collect").await.unwrap();
+        assert_eq!(
+            output.outputs["map"].get("output"),
+            Some(&json!([10, 2]).into())
+        );
+        let failures = output.outputs["map"]
+            .get("failures")
+            .unwrap()
+            .as_array()
+            .unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].get("index"), Some(&Value::from(1)));
+
+        let error = run("fail").await.unwrap_err();
+        assert!(matches!(error.kind, RuntimeErrorKind::ScriptFailed { .. }));
+        assert_eq!(error.call_stack[0].item, Some(1));
+    }
+
+    #[tokio::test]
+    async fn filter_nodes_should_reject_bodies_not_returning_bools() {
+        let graphs = Arc::new(BTreeMap::from([(
+            "exclaim".to_string(),
+            body("item + \"!\""),
+        )]));
+        let program = iterations(vec![node(
+            "filter",
+            "filterNode",
+            json!({ "graphName": "exclaim" }),
+        )]);
+
+        let error = Interpreter::new(program, Arc::new(TestHost::default()))
+            .with_graphs(graphs)
+            .run(json!(["a", "bc"]).into())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.kind,
+            RuntimeErrorKind::TypeMismatch { port, .. } if port == KEEP_PORT
+        ));
+    }
+
+    #[tokio::test]
+    async fn iteration_nodes_should_respect_their_parallelism() {
+        let echo_body = compiled(
+            &graph(
+                vec![node("entry", "fnEntry", json!({})), echo("echo")],
+                vec![edge("entry", "echo")],
+            ),
+            &[],
+        );
+        let graphs = Arc::new(BTreeMap::from([("echo".to_string(), echo_body)]));
+        let program = iterations(vec![node(
+            "map",
+            "mapNode",
+            json!({ "graphName": "echo", "parallelism": 2 }),
+        )]);
+
+        let host = Arc::new(TestHost::default());
+        let output = Interpreter::new(program, host.clone())
+            .with_graphs(graphs)
+            .run(json!(["a", "b", "c", "d", "e", "f"]).into())
+            .await
+            .unwrap();
+
+        let items = output.outputs["map"].as_array().unwrap();
+        assert_eq!(items[5], json!({ "item": "f", "index": 5 }).into());
+        assert_eq!(host.calls.load(Ordering::SeqCst), 6);
+        assert_eq!(host.peak.load(Ordering::SeqCst), 2);
+    }
+
+    /// A graph counting up the number variable `count`: it reads the
+    /// variable, adds one in a script and writes the result back.
+    fn counter(entry: &str) -> Arc<Program> {
+        let graph = FlowGraph {
+            variables: Some(vec![variable("count", ValueType::Number, json!(41))]),
+            ..graph(
+                vec![
+                    node("entry", entry, json!({})),
+                    node("get", "getVariable", json!({ "variableName": "count" })),
+                    node(
+                        "increment",
+                        "scriptNode",
+                        json!({ "scriptName": "increment" }),
+                    ),
+                    node("set", "setVariable", json!({ "variableName": "count" })),
+                    node("read", "getVariable", json!({ "variableName": "count" })),
+                ],
+                vec![
+                    edge("entry", "get"),
+                    edge("get", "increment"),
+                    edge("increment", "set"),
+                    edge("set", "read"),
+                ],
+            )
+        };
+        compiled(&graph, &[("increment", "input + 1")])
+    }
+
+    #[tokio::test]
+    async fn variables_should_start_from_their_default_in_every_run() {
+        let interpreter = Interpreter::new(counter("startNode"), Arc::new(TestHost::default()));
+
+        for _ in 0..2 {
+            let output = interpreter.run(Value::Null).await.unwrap();
+            assert_eq!(output.outputs["read"], Value::from(42));
+        }
+    }
+
+    #[tokio::test]
+    async fn function_graph_calls_should_have_their_own_variables() {
+        let graphs = Arc::new(BTreeMap::from([("count".to_string(), counter("fnEntry"))]));
+        let program = iterations(vec![node(
+            "map",
+            "mapNode",
+            json!({ "graphName": "count", "parallelism": 1 }),
+        )]);
+
+        let output = Interpreter::new(program, Arc::new(TestHost::default()))
+            .with_graphs(graphs)
+            .run(json!(["a", "b", "c"]).into())
+            .await
+            .unwrap();
+
+        assert_eq!(output.outputs["map"], json!([42, 42, 42]).into());
+    }
+
+    #[tokio::test]
+    async fn secrets_should_reach_plugins_but_not_the_trace() {
+        let secrets = Arc::new(BTreeMap::from([(
+            "TOKEN".to_string(),
+            "s3cr3t".to_string(),
+        )]));
+        let token = json!({ "token": "Bearer ${secret.TOKEN}" });
+        let host = Arc::new(RecordingHost::default());
+        let recorder = Arc::new(Recorder::default());
+
+        let output = Interpreter::new(with_arguments("echo", token.clone()), host.clone())
+            .with_secrets(secrets.clone())
+            .with_observer(recorder.clone())
+            .run(Value::Null)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            host.calls.lock().unwrap()[0],
+            json!({ "token": "Bearer s3cr3t" }).into()
+        );
+        assert_eq!(
+            output.outputs["call1"],
+            json!({ "token": "Bearer ********" }).into()
+        );
+        let finished = recorder
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .find_map(|event| match event {
+                ExecutionEvent::NodeFinished {
+                    node_id, output, ..
+                } if node_id == "call1" => Some(output.clone()),
+                _ => None,
+            });
+        assert_eq!(finished, Some(json!({ "token": "Bearer ********" }).into()));
+
+        let error = Interpreter::new(with_arguments("reject", token.clone()), host)
+            .with_secrets(secrets)
+            .run(Value::Null)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.kind,
+            RuntimeErrorKind::FunctionFailed {
+                message: r#"rejected {"token":"Bearer ********"}"#.into()
+            }
+        );
+
+        for interpreter in [
+            Interpreter::new(
+                with_arguments("echo", token.clone()),
+                Arc::new(TestHost::default()),
+            )
+            .with_secrets(Arc::new(BTreeMap::new())),
+            Interpreter::new(
+                with_arguments("echo", token.clone()),
+                Arc::new(TestHost::default()),
+            ),
+        ] {
+            let error = interpreter.run(Value::Null).await.unwrap_err();
+            assert_eq!(
+                error.kind,
+                RuntimeErrorKind::UnknownSecret {
+                    name: "TOKEN".into()
+                }
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn outputs_of_calls_given_secrets_should_not_be_cached() {
+        let mut program = Arc::unwrap_or_clone(with_arguments(
+            "echo",
+            json!({ "token": "Bearer ${secret.TOKEN}" }),
+        ));
+        program.instructions[1].op = Op::Call {
+            function: 0,
+            arguments: Some(0),
+            pure: true,
+        };
+        let program = Arc::new(program);
+
+        let host = Arc::new(TestHost::default());
+        let cache = Arc::new(MemoryCache::default());
+        for _ in 0..2 {
+            Interpreter::new(program.clone(), host.clone())
+                .with_cache(cache.clone())
+                .with_secrets(Arc::new(BTreeMap::from([(
+                    "TOKEN".to_string(),
+                    "s3cr3t".to_string(),
+                )])))
+                .run(Value::Null)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(host.calls.load(Ordering::SeqCst), 2);
+        assert!(cache.outputs.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn environment_variables_should_resolve_before_secrets() {
+        let program = with_arguments(
+            "echo",
+            json!({ "url": "${env.API_URL}", "token": "${env.TOKEN}" }),
+        );
+        let environment = Arc::new(Environment {
+            name: Some("prod".into()),
+            variables: BTreeMap::from([
+                ("API_URL".into(), "https://example.com".into()),
+                ("TOKEN".into(), "${secret.PROD_TOKEN}".into()),
+            ]),
+        });
+
+        let output = Interpreter::new(program.clone(), Arc::new(TestHost::default()))
+            .with_environment(environment)
+            .with_secrets(Arc::new(BTreeMap::from([(
+                "PROD_TOKEN".to_string(),
+                "s3cr3t".to_string(),
+            )])))
+            .run(Value::Null)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            output.outputs["call1"],
+            json!({ "url": "https://example.com", "token": "********" }).into()
+        );
+
+        let error = Interpreter::new(program, Arc::new(TestHost::default()))
+            .with_environment(Arc::new(Environment {
+                name: None,
+                variables: BTreeMap::from([("API_URL".into(), "https://example.com".into())]),
+            }))
+            .run(Value::Null)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.kind,
+            RuntimeErrorKind::UnknownEnvironmentVariable {
+                name: "TOKEN".into()
+            }
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use flow_rt_shared::{
+    schemas::FlowGraphPort,
+    value::{Value, ValueType},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::retry::RetryPolicy;
+
+pub type InstructionId = usize;
+pub type ConstantId = usize;
+pub type FunctionId = usize;
+pub type ScriptId = usize;
+pub type VariableId = usize;
+
+/// Node types understood by the compiler, as written by the editor into `.jfg` files.
+pub mod node_types {
+    pub const START_NODE: &str = "startNode";
+    pub const SCRIPT_NODE: &str = "scriptNode";
+    pub const FOREIGN_FUNCTION_NODE: &str = "foreignFunctionNode";
+    pub const EVENT_LISTENER: &str = "eventListener";
+    pub const EVENT_TRIGGER: &str = "eventTrigger";
+    pub const FN_ENTRY: &str = "fnEntry";
+    pub const FN_TRIGGER: &str = "fnTrigger";
+    pub const IF_NODE: &str = "ifNode";
+    pub const SWITCH_NODE: &str = "switchNode";
+    pub const AND_GATE: &str = "andGate";
+    pub const OR_GATE: &str = "orGate";
+    pub const NOT_GATE: &str = "notGate";
+    pub const MAP_NODE: &str = "mapNode";
+    pub const FILTER_NODE: &str = "filterNode";
+    pub const REDUCE_NODE: &str = "reduceNode";
+    pub const GET_VARIABLE: &str = "getVariable";
+    pub const SET_VARIABLE: &str = "setVariable";
+
+    /// Whether a node of this type starts a run.
+    pub fn is_entry(node_type: &str) -> bool {
+        matches!(node_type, START_NODE | FN_ENTRY | EVENT_LISTENER)
+    }
+}
+
+/// A compiled flow graph.
+///
+/// `instructions` are stored in an order where every instruction comes after
+/// all of the instructions it receives values from.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Program {
+    pub instructions: Vec<Instruction>,
+    pub constants: Vec<Value>,
+    pub functions: Vec<FunctionRef>,
+    #[serde(default)]
+    pub scripts: Vec<Script>,
+    #[serde(default)]
+    pub variables: Vec<Variable>,
+    pub entry_points: Vec<InstructionId>,
+    /// Ids of nodes that can't be reached from any entry point and will never run.
+    pub unreachable: Vec<String>,
+}
+
+impl Program {
+    /// A hash of everything that affects how the program executes, to tell
+    /// whether a graph changed between two compilations. Moving nodes around
+    /// in the editor doesn't change it.
+    pub fn fingerprint(&self) -> String {
+        let serialized = serde_json::to_vec(self).unwrap_or_default();
+        blake3::hash(&serialized).to_hex().to_string()
+    }
+
+    pub fn instruction_by_node_id(&self, node_id: &str) -> Option<&Instruction> {
+        self.instructions.iter().find(|i| i.node_id == node_id)
+    }
+
+    pub fn constant(&self, id: ConstantId) -> Option<&Value> {
+        self.constants.get(id)
+    }
+
+    pub fn function(&self, id: FunctionId) -> Option<&FunctionRef> {
+        self.functions.get(id)
+    }
+
+    pub fn script(&self, id: ScriptId) -> Option<&Script> {
+        self.scripts.get(id)
+    }
+
+    pub fn variable(&self, id: VariableId) -> Option<&Variable> {
+        self.variables.get(id)
+    }
+
+    /// Names of the events the `eventListener`s of this program are triggered by.
+    pub fn events(&self) -> impl Iterator<Item = &str> {
+        self.entry_points
+            .iter()
+            .filter_map(|id| match &self.instructions[*id].op {
+                Op::Entry(EntryKind::Event { name }) => Some(name.as_str()),
+                _ => None,
+            })
+    }
+
+    pub fn listens_to(&self, event: &str) -> bool {
+        self.events().any(|name| name == event)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Instruction {
+    pub node_id: String,
+    pub op: Op,
+    /// Values feeding this instruction, in the order their edges appear in the graph.
+    pub inputs: Vec<Binding>,
+    /// Instructions this one feeds into, once per edge.
+    pub outputs: Vec<InstructionId>,
+    /// Declared input ports; incoming values are coerced to their types.
+    pub input_ports: Vec<FlowGraphPort>,
+    /// How long the node may run, from `timeoutMs` in the node `data`.
+    /// Falls back to [`crate::interpreter::RunOptions::node_timeout_ms`].
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// How the node is retried when it fails, from `retry` in the node `data`.
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+}
+
+/// An edge as seen from the instruction it feeds into.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Binding {
+    /// Id of the edge in the graph, used to report the values flowing over it.
+    #[serde(default)]
+    pub edge_id: String,
+    pub source: InstructionId,
+    /// Field of the source value to forward, for sources with several output ports.
+    pub source_port: Option<String>,
+    /// Input port the value is bound to. `None` for edges saved without handles.
+    pub target_port: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Op {
+    /// Start of a run. Produces the input the graph was invoked with.
+    Entry(EntryKind),
+    /// Calls a plugin function, with optional constant arguments taken from the node `data`.
+    Call {
+        function: FunctionId,
+        arguments: Option<ConstantId>,
+        /// Set for functions their plugin marks as pure, whose outputs may be cached.
+        #[serde(default)]
+        pure: bool,
+    },
+    /// Runs a script from the project's `scripts` directory, with optional
+    /// constant arguments taken from the node `data`.
+    Script {
+        script: ScriptId,
+        arguments: Option<ConstantId>,
+    },
+    /// Fires `event` on the [`crate::events::EventSink`] of the run, with the incoming
+    /// value as payload. The payload is passed through unchanged.
+    Emit { event: String },
+    /// Runs the function graph named `graph` and produces its outputs, with
+    /// optional constant arguments taken from the node `data`.
+    CallGraph {
+        graph: String,
+        arguments: Option<ConstantId>,
+    },
+    /// Passes its payload on through the `then` port when its condition is
+    /// true and through the `else` port otherwise.
+    If,
+    /// Passes its payload on through the port of the first case equal to its
+    /// value, or through the `default` port when no case matches.
+    Switch { cases: Vec<SwitchCase> },
+    /// Combines boolean inputs into a single boolean.
+    Gate(GateKind),
+    /// Produces the value `variable` holds in the current scope.
+    GetVariable { variable: VariableId },
+    /// Stores the incoming value in `variable` for the current scope and passes it on.
+    SetVariable { variable: VariableId },
+    /// Runs the function graph `graph` once per item of the incoming array.
+    Iterate {
+        kind: IterationKind,
+        graph: String,
+        /// How many items run at the same time, all of them when `None`.
+        /// Reductions run one item after the other.
+        parallelism: Option<u32>,
+        on_error: ItemErrorPolicy,
+        /// The starting accumulator of a reduction, from `initial` in the node `data`.
+        initial: Option<ConstantId>,
+    },
+}
+
+impl Op {
+    /// Whether the instruction only passes its value on through some of its
+    /// output ports. Edges leaving the other ports aren't taken.
+    pub fn is_branch(&self) -> bool {
+        matches!(self, Op::If | Op::Switch { .. })
+    }
+}
+
+/// Output ports of an `ifNode`.
+pub const THEN_PORT: &str = "then";
+pub const ELSE_PORT: &str = "else";
+/// Output port of a `switchNode` taken when no case matches.
+pub const DEFAULT_PORT: &str = "default";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SwitchCase {
+    /// Output port taken when the case matches.
+    pub port: String,
+    pub value: Value,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IterationKind {
+    /// Produces the output of the body for every item.
+    Map,
+    /// Keeps the items the body returns `true` for.
+    Filter,
+    /// Passes the output of the body for one item on to the next one, and
+    /// produces the output for the last item.
+    Reduce,
+}
+
+/// What an iteration node does when its body fails for an item, from
+/// `onError` in the node `data`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ItemErrorPolicy {
+    /// The node fails with the error of the item.
+    #[default]
+    Fail,
+    /// The item is left out, and the node produces `{ output, failures }`
+    /// with the index and error of every item that failed.
+    Collect,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GateKind {
+    And,
+    Or,
+    Not,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum EntryKind {
+    Start,
+    Function,
+    /// An `eventListener`, started when the event named `name` is fired.
+    Event {
+        name: String,
+    },
+}
+
+/// A script resolved against the [`crate::script::ScriptLoader`], embedded in
+/// the program so a run isn't affected by edits made while it is in progress.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Script {
+    pub name: String,
+    pub source: String,
+}
+
+/// A variable declared on the graph. Every run, and every call of a function
+/// graph, starts with its own copy holding `default`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    pub value_type: ValueType,
+    pub default: Value,
+}
+
+/// A plugin function resolved against the [`crate::compiler::FunctionCatalog`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FunctionRef {
+    pub plugin: String,
+    pub function: String,
+}
@@ -0,0 +1,20 @@
+pub mod cancellation;
+pub mod compiler;
+pub mod debugger;
+pub mod environment;
+pub mod error;
+pub mod events;
+pub mod interpreter;
+pub mod ir;
+pub mod memo;
+pub mod observer;
+mod references;
+pub mod replay;
+pub mod retry;
+pub mod scheduler;
+pub mod script;
+pub mod secrets;
+#[cfg(test)]
+mod testing;
+pub mod typecheck;
+pub mod variables;
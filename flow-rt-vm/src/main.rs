@@ -1,3 +1,84 @@
-fn main() {
-    println!("Hello, world!");
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::{Parser, Subcommand};
+
+mod cli;
+
+/// Runs the graphs of a Flow-RT project without the editor.
+#[derive(Parser)]
+#[command(name = "flow-rt-vm", version, about)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Runs a graph and prints its outputs as JSON.
+    Run {
+        /// Directory containing the project's `Flow.toml`.
+        project: PathBuf,
+        /// Name of the graph in the project's `flows` directory, without `.jfg`.
+        graph: String,
+        /// JSON value passed to the entry nodes of the graph.
+        #[arg(long)]
+        input: Option<String>,
+        /// Directory to load plugin libraries from. Defaults to `modules` inside the project.
+        #[arg(long, env = "FLOW_RT_MODULES")]
+        modules: Option<PathBuf>,
+        /// Maximum number of nodes executing at the same time.
+        #[arg(long)]
+        max_concurrency: Option<usize>,
+        /// How long a node may run, in milliseconds, unless it sets `timeoutMs` itself.
+        #[arg(long)]
+        timeout_ms: Option<u64>,
+        /// Calls pure functions even when the project's cache holds their output.
+        #[arg(long)]
+        no_cache: bool,
+        /// Environment of the project's `Flow.toml` to run in. Defaults to its current one.
+        #[arg(long = "env", env = "FLOW_RT_ENV")]
+        environment: Option<String>,
+    },
+    /// Lists the graphs of a project with their input schemas.
+    List {
+        /// Directory containing the project's `Flow.toml`.
+        project: PathBuf,
+    },
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let result = match Args::parse().command {
+        Command::Run {
+            project,
+            graph,
+            input,
+            modules,
+            max_concurrency,
+            timeout_ms,
+            no_cache,
+            environment,
+        } => {
+            cli::run(cli::RunCommand {
+                project,
+                graph,
+                input,
+                modules,
+                max_concurrency,
+                timeout_ms,
+                no_cache,
+                environment,
+            })
+            .await
+        }
+        Command::List { project } => cli::list(&project),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            error.exit_code()
+        }
+    }
 }
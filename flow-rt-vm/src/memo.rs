@@ -0,0 +1,448 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::SystemTime,
+};
+
+use flow_rt_shared::value::Value;
+
+use crate::ir::FunctionRef;
+
+/// How large a [`DiskCache`] may grow when its embedder doesn't configure a limit.
+pub const DEFAULT_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Where the outputs of pure functions are cached for the project at `project`.
+pub fn project_cache_directory(project: &Path) -> PathBuf {
+    project.join(".flow").join("cache")
+}
+
+/// Identifies one output of a pure plugin function.
+///
+/// `plugin_hash` is the hash of the plugin library, so rebuilding a plugin
+/// invalidates every output it produced before.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MemoKey {
+    pub plugin: String,
+    pub plugin_hash: String,
+    /// blake3 hash of the function name and its arguments.
+    pub input_hash: String,
+}
+
+impl MemoKey {
+    pub fn new(function: &FunctionRef, plugin_hash: &str, arguments: &Value) -> Self {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(function.function.as_bytes());
+        hasher.update(&[0]);
+        // Objects are ordered by key, so equal arguments always serialize the same way.
+        hasher.update(&serde_json::to_vec(arguments).unwrap_or_default());
+
+        Self {
+            plugin: function.plugin.clone(),
+            plugin_hash: plugin_hash.to_string(),
+            input_hash: hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+/// Whether a serialized plugin `FnDescriptor` marks its function as pure,
+/// either with a top-level `pure` flag or one in its `metadata`.
+pub fn marked_pure(descriptor: &serde_json::Value) -> bool {
+    [descriptor.get("pure"), descriptor.pointer("/metadata/pure")]
+        .into_iter()
+        .flatten()
+        .any(|pure| pure.as_bool() == Some(true))
+}
+
+/// Keeps the outputs of pure plugin functions, so calling one again with the
+/// same arguments doesn't run it again.
+///
+/// Caching is best effort: failing to read or store an output only means the
+/// function is called. The interpreter calls caches on a blocking thread, so
+/// they are free to do file I/O.
+pub trait OutputCache: Send + Sync {
+    fn get(&self, key: &MemoKey) -> Option<Value>;
+    fn put(&self, key: &MemoKey, output: &Value);
+}
+
+/// An [`OutputCache`] on disk, holding at most `max_bytes` of outputs.
+///
+/// Outputs are stored as `<root>/<plugin>/<plugin hash>/<input hash>.json`.
+/// The first time a plugin is seen with a new hash, the outputs of its
+/// previous builds are deleted. Once the cache grows past its limit, the
+/// outputs used least recently are deleted first, until it is back below
+/// [`EVICTION_LOW_WATER`] of its limit.
+///
+/// The directory is only scanned when the cache is opened; from then on the
+/// cache keeps track of the outputs it holds in memory.
+pub struct DiskCache {
+    root: PathBuf,
+    max_bytes: AtomicU64,
+    state: Mutex<DiskState>,
+}
+
+/// The share of its limit, in percent, a full [`DiskCache`] is evicted down
+/// to, so the next outputs stored don't each start another eviction.
+pub const EVICTION_LOW_WATER: u64 = 90;
+
+struct DiskState {
+    /// The plugin hash whose outputs are kept, by plugin.
+    current: HashMap<String, String>,
+    /// Every stored output, by path.
+    stored: HashMap<PathBuf, Stored>,
+    size: u64,
+}
+
+struct Stored {
+    size: u64,
+    used: SystemTime,
+}
+
+impl DiskState {
+    fn insert(&mut self, path: PathBuf, stored: Stored) {
+        self.size += stored.size;
+        if let Some(replaced) = self.stored.insert(path, stored) {
+            self.size = self.size.saturating_sub(replaced.size);
+        }
+    }
+
+    fn remove(&mut self, path: &Path) {
+        if let Some(removed) = self.stored.remove(path) {
+            self.size = self.size.saturating_sub(removed.size);
+        }
+    }
+}
+
+impl DiskCache {
+    pub fn open(root: impl Into<PathBuf>, max_bytes: u64) -> io::Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+
+        let mut state = DiskState {
+            current: HashMap::new(),
+            stored: HashMap::new(),
+            size: 0,
+        };
+        for entry in entries(&root) {
+            state.insert(
+                entry.path,
+                Stored {
+                    size: entry.size,
+                    used: entry.used,
+                },
+            );
+        }
+
+        Ok(Self {
+            root,
+            max_bytes: AtomicU64::new(max_bytes),
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Changes the limit of the cache, evicting outputs right away if it
+    /// now holds more than that.
+    pub fn set_max_bytes(&self, max_bytes: u64) {
+        self.max_bytes.store(max_bytes, Ordering::Relaxed);
+        let mut state = self.lock();
+        self.evict(&mut state, None);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, DiskState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn plugin_directory(&self, plugin: &str) -> PathBuf {
+        self.root.join(file_name(plugin))
+    }
+
+    fn path(&self, key: &MemoKey) -> PathBuf {
+        self.plugin_directory(&key.plugin)
+            .join(file_name(&key.plugin_hash))
+            .join(format!("{}.json", key.input_hash))
+    }
+
+    /// Deletes the outputs of every other build of the plugin of `key`.
+    fn invalidate_stale(&self, state: &mut DiskState, key: &MemoKey) {
+        if state.current.get(&key.plugin) == Some(&key.plugin_hash) {
+            return;
+        }
+
+        let keep = file_name(&key.plugin_hash);
+        if let Ok(builds) = fs::read_dir(self.plugin_directory(&key.plugin)) {
+            for build in builds.flatten() {
+                let build = build.path();
+                if build.file_name().is_some_and(|name| name != keep.as_str())
+                    && fs::remove_dir_all(&build).is_ok()
+                {
+                    let removed = state
+                        .stored
+                        .keys()
+                        .filter(|path| path.starts_with(&build))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    for path in removed {
+                        state.remove(&path);
+                    }
+                }
+            }
+        }
+
+        state
+            .current
+            .insert(key.plugin.clone(), key.plugin_hash.clone());
+    }
+
+    /// Once the cache is past its limit, deletes the least recently used
+    /// outputs until it is back below its low-water mark, keeping the output
+    /// just stored at `keep`.
+    fn evict(&self, state: &mut DiskState, keep: Option<&Path>) {
+        let max_bytes = self.max_bytes();
+        if state.size <= max_bytes {
+            return;
+        }
+
+        let target = max_bytes / 100 * EVICTION_LOW_WATER;
+        let mut candidates = state
+            .stored
+            .iter()
+            .filter(|(path, _)| Some(path.as_path()) != keep)
+            .map(|(path, stored)| (stored.used, path.clone()))
+            .collect::<Vec<_>>();
+        candidates.sort();
+
+        for (_, path) in candidates {
+            if state.size <= target {
+                break;
+            }
+            // Already gone is as good as deleted.
+            match fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => continue,
+                _ => state.remove(&path),
+            }
+        }
+    }
+}
+
+impl OutputCache for DiskCache {
+    fn get(&self, key: &MemoKey) -> Option<Value> {
+        let mut state = self.lock();
+        self.invalidate_stale(&mut state, key);
+
+        let path = self.path(key);
+        if !state.stored.contains_key(&path) {
+            return None;
+        }
+
+        let Ok(stored) = fs::read(&path) else {
+            state.remove(&path);
+            return None;
+        };
+        let output = serde_json::from_slice(&stored).ok()?;
+
+        // Marks the output as recently used, so it is evicted last, also
+        // after the cache is opened again.
+        let now = SystemTime::now();
+        if let Ok(file) = File::options().append(true).open(&path) {
+            let _ = file.set_modified(now);
+        }
+        if let Some(stored) = state.stored.get_mut(&path) {
+            stored.used = now;
+        }
+
+        Some(output)
+    }
+
+    fn put(&self, key: &MemoKey, output: &Value) {
+        let Ok(serialized) = serde_json::to_vec(output) else {
+            return;
+        };
+        if serialized.len() as u64 > self.max_bytes() {
+            return;
+        }
+
+        let mut state = self.lock();
+        self.invalidate_stale(&mut state, key);
+
+        let path = self.path(key);
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, &serialized));
+
+        if written.is_ok() {
+            state.insert(
+                path.clone(),
+                Stored {
+                    size: serialized.len() as u64,
+                    used: SystemTime::now(),
+                },
+            );
+            self.evict(&mut state, Some(&path));
+        }
+    }
+}
+
+struct Entry {
+    path: PathBuf,
+    size: u64,
+    used: SystemTime,
+}
+
+/// Every stored output below `directory`.
+fn entries(directory: &Path) -> Vec<Entry> {
+    let mut entries = vec![];
+    let Ok(children) = fs::read_dir(directory) else {
+        return entries;
+    };
+
+    for child in children.flatten() {
+        let Ok(metadata) = child.metadata() else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            entries.extend(self::entries(&child.path()));
+        } else {
+            entries.push(Entry {
+                path: child.path(),
+                size: metadata.len(),
+                used: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            });
+        }
+    }
+
+    entries
+}
+
+/// Keeps plugin names from escaping the cache directory.
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use flow_rt_shared::value::Value;
+    use serde_json::json;
+
+    use crate::{
+        ir::FunctionRef,
+        memo::{DiskCache, MemoKey, OutputCache, marked_pure},
+    };
+
+    fn scratch_directory() -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let directory = std::env::temp_dir().join(format!(
+            "flow-rt-memo-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn key(plugin_hash: &str, argument: i64) -> MemoKey {
+        let function = FunctionRef {
+            plugin: "math".into(),
+            function: "square".into(),
+        };
+        MemoKey::new(&function, plugin_hash, &json!({ "x": argument }).into())
+    }
+
+    #[test]
+    fn descriptors_should_mark_functions_pure() {
+        assert!(marked_pure(&json!({ "schema": {}, "pure": true })));
+        assert!(marked_pure(&json!({ "metadata": { "pure": true } })));
+        assert!(!marked_pure(&json!({ "metadata": { "pure": "yes" } })));
+        assert!(!marked_pure(&json!({ "schema": {} })));
+    }
+
+    #[test]
+    fn outputs_should_be_found_by_their_arguments() {
+        let cache = DiskCache::open(scratch_directory(), 1024).unwrap();
+        cache.put(&key("v1", 3), &Value::from(9));
+
+        assert_eq!(cache.get(&key("v1", 3)), Some(Value::from(9)));
+        assert_eq!(cache.get(&key("v1", 4)), None);
+    }
+
+    #[test]
+    fn outputs_should_be_dropped_when_the_plugin_changes() {
+        let directory = scratch_directory();
+        let cache = DiskCache::open(&directory, 1024).unwrap();
+        cache.put(&key("v1", 3), &Value::from(9));
+
+        let reopened = DiskCache::open(&directory, 1024).unwrap();
+        assert_eq!(reopened.get(&key("v2", 3)), None);
+        assert_eq!(reopened.get(&key("v1", 3)), None);
+    }
+
+    #[test]
+    fn cache_should_evict_outputs_past_its_size_limit() {
+        let cache = DiskCache::open(scratch_directory(), 10).unwrap();
+        cache.put(&key("v1", 1), &Value::from("first"));
+        cache.put(&key("v1", 2), &Value::from("second"));
+
+        assert_eq!(cache.get(&key("v1", 1)), None);
+        assert_eq!(cache.get(&key("v1", 2)), Some(Value::from("second")));
+    }
+
+    #[test]
+    fn eviction_should_make_room_for_more_than_one_output() {
+        // Every output is stored as 10 bytes.
+        let output = Value::from("12345678");
+        let cache = DiskCache::open(scratch_directory(), 100).unwrap();
+        for argument in 0..11 {
+            cache.put(&key("v1", argument), &output);
+        }
+
+        // Evicted down to 90 bytes: the output stored last and 8 others.
+        let kept = |cache: &DiskCache| {
+            (0..12)
+                .filter(|a| cache.get(&key("v1", *a)).is_some())
+                .count()
+        };
+        assert_eq!(cache.get(&key("v1", 10)), Some(output.clone()));
+        assert_eq!(kept(&cache), 9);
+
+        // Which leaves room for the next output without evicting again.
+        cache.put(&key("v1", 11), &output);
+        assert_eq!(kept(&cache), 10);
+    }
+
+    #[test]
+    fn lowering_the_limit_should_evict_right_away() {
+        let output = Value::from("12345678");
+        let cache = DiskCache::open(scratch_directory(), 500).unwrap();
+        for argument in 0..15 {
+            cache.put(&key("v1", argument), &output);
+        }
+
+        cache.set_max_bytes(100);
+
+        let kept = (0..15)
+            .filter(|a| cache.get(&key("v1", *a)).is_some())
+            .count();
+        assert_eq!(cache.max_bytes(), 100);
+        assert_eq!(kept, 9);
+    }
+}
@@ -0,0 +1,102 @@
+use std::{sync::Arc, time::Instant};
+
+use flow_rt_shared::value::Value;
+use serde::Serialize;
+
+use crate::{debugger::SuspendReason, error::RuntimeError, interpreter::CallFrame};
+
+/// Something that happened during a run, reported while the run is in progress.
+///
+/// Node events carry the call stack of the function graph the node belongs
+/// to, which is empty for nodes of the graph that was started.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum ExecutionEvent {
+    NodeStarted {
+        node_id: String,
+        call_stack: Vec<CallFrame>,
+        /// The value flowing into the node, before its input ports are applied.
+        input: Value,
+    },
+    NodeFinished {
+        node_id: String,
+        call_stack: Vec<CallFrame>,
+        output: Value,
+        duration_ms: u64,
+    },
+    NodeFailed {
+        node_id: String,
+        call_stack: Vec<CallFrame>,
+        error: RuntimeError,
+        duration_ms: u64,
+    },
+    /// Attempt number `attempt` of the node failed with `error` and the node
+    /// is retried after `delay_ms`, as its retry policy asks.
+    NodeRetrying {
+        node_id: String,
+        call_stack: Vec<CallFrame>,
+        attempt: u32,
+        error: RuntimeError,
+        delay_ms: u64,
+    },
+    /// The run was cancelled while the node was waiting to run or running.
+    NodeCancelled {
+        node_id: String,
+        call_stack: Vec<CallFrame>,
+        /// Whether the node got to start. A node cancelled while waiting for
+        /// its turn or the debugger was never reported as `NodeStarted`.
+        started: bool,
+        duration_ms: u64,
+    },
+    /// The node was stopped after running longer than `timeout_ms`.
+    NodeTimedOut {
+        node_id: String,
+        call_stack: Vec<CallFrame>,
+        timeout_ms: u64,
+        duration_ms: u64,
+    },
+    /// The node didn't run, as none of the edges leading to it were taken.
+    NodeSkipped {
+        node_id: String,
+        call_stack: Vec<CallFrame>,
+    },
+    /// The node was suspended by the [`crate::debugger::Debugger`] before running.
+    NodePaused {
+        node_id: String,
+        call_stack: Vec<CallFrame>,
+        reason: SuspendReason,
+    },
+    /// The run ended, successfully when `error` is `None`.
+    RunFinished {
+        error: Option<RuntimeError>,
+        duration_ms: u64,
+    },
+}
+
+/// Receives the [`ExecutionEvent`]s of a run as they happen.
+///
+/// Called from the tasks executing the run, so implementations should hand
+/// events off quickly instead of doing work inline.
+pub trait RunObserver: Send + Sync {
+    fn notify(&self, event: ExecutionEvent);
+}
+
+/// Forwards every event to each observer in turn.
+impl RunObserver for Vec<Arc<dyn RunObserver>> {
+    fn notify(&self, event: ExecutionEvent) {
+        if let Some((last, others)) = self.split_last() {
+            for observer in others {
+                observer.notify(event.clone());
+            }
+            last.notify(event);
+        }
+    }
+}
+
+pub(crate) fn elapsed_ms(started: Instant) -> u64 {
+    started.elapsed().as_millis().try_into().unwrap_or(u64::MAX)
+}
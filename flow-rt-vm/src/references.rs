@@ -0,0 +1,66 @@
+use flow_rt_shared::value::Value;
+
+/// Replaces every `${namespace.NAME}` in the strings of `value` with what
+/// `lookup` returns for `NAME`. Fails with the first name `lookup` has no
+/// value for.
+///
+/// Substituted text isn't searched for references again, and references to
+/// other namespaces are left as they are.
+pub(crate) fn resolve_references(
+    value: &Value,
+    namespace: &str,
+    lookup: impl Fn(&str) -> Option<String> + Copy,
+) -> Result<Value, String> {
+    Ok(match value {
+        Value::String(text) => Value::String(substitute(text, namespace, lookup)?),
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| resolve_references(value, namespace, lookup))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| {
+                    Ok((key.clone(), resolve_references(value, namespace, lookup)?))
+                })
+                .collect::<Result<_, String>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+fn substitute(
+    text: &str,
+    namespace: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let open = format!("${{{namespace}.");
+    let mut resolved = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some((before, name, after)) = next_reference(rest, &open) {
+        let value = lookup(name).ok_or_else(|| name.to_string())?;
+
+        resolved.push_str(before);
+        resolved.push_str(&value);
+        rest = after;
+    }
+
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
+/// Splits `text` around its first reference starting with `open`.
+fn next_reference<'a>(text: &'a str, open: &str) -> Option<(&'a str, &'a str, &'a str)> {
+    let start = text.find(open)?;
+    let name_start = start + open.len();
+    let length = text[name_start..].find('}')?;
+
+    Some((
+        &text[..start],
+        &text[name_start..name_start + length],
+        &text[name_start + length + 1..],
+    ))
+}
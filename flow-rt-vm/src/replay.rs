@@ -0,0 +1,190 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use flow_rt_shared::value::Value;
+
+use crate::{
+    error::{RuntimeError, RuntimeErrorKind},
+    interpreter::CallFrame,
+};
+
+/// Identifies a node by the function graph calls leading to it and its id.
+type NodeKey = (Vec<CallFrame>, String);
+
+/// The recorded outcomes of the plugin calls of a run, used to replay it.
+///
+/// A replayed run executes the graph as usual, except that every
+/// `foreignFunctionNode` is answered from the recording instead of calling
+/// its plugin. Outcomes are matched by node id and call stack; a node that
+/// ran several times gets its outcomes in the order they were recorded, and
+/// keeps getting the last one after that, so retries see the final outcome.
+///
+/// Recorded timeouts and cancellations are reported as the node timing out
+/// or being cancelled again, like in the recorded run.
+#[derive(Default)]
+pub struct Replay {
+    calls: Mutex<HashMap<NodeKey, VecDeque<Result<Value, RuntimeError>>>>,
+}
+
+impl Replay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the outcome of one execution of `node_id`, after the ones already recorded.
+    pub fn record(
+        &self,
+        call_stack: Vec<CallFrame>,
+        node_id: impl Into<String>,
+        outcome: Result<Value, RuntimeError>,
+    ) {
+        self.calls
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry((call_stack, node_id.into()))
+            .or_default()
+            .push_back(outcome);
+    }
+
+    /// The recorded outcome of the next execution of `node_id`.
+    pub(crate) fn answer(
+        &self,
+        call_stack: &[CallFrame],
+        node_id: &str,
+    ) -> Result<Value, RuntimeError> {
+        let mut calls = self.calls.lock().unwrap_or_else(|e| e.into_inner());
+        let outcomes = calls
+            .get_mut(&(call_stack.to_vec(), node_id.to_string()))
+            .filter(|outcomes| !outcomes.is_empty())
+            .ok_or_else(|| RuntimeError::at(node_id, RuntimeErrorKind::NotRecorded))?;
+
+        let outcome = match outcomes.len() {
+            1 => outcomes.front().cloned(),
+            _ => outcomes.pop_front(),
+        };
+
+        outcome.unwrap_or_else(|| Err(RuntimeError::at(node_id, RuntimeErrorKind::NotRecorded)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use flow_rt_shared::value::Value;
+    use serde_json::json;
+
+    use crate::{
+        error::{RuntimeError, RuntimeErrorKind},
+        interpreter::{BoxFuture, FunctionHost, Interpreter},
+        ir::{EntryKind, FunctionRef, Op, Program},
+        observer::ExecutionEvent,
+        replay::Replay,
+        testing::{Recorder, instruction},
+    };
+
+    /// Fails every call, so a passing test shows the plugin wasn't called.
+    struct Unreachable;
+
+    impl FunctionHost for Unreachable {
+        fn call<'a>(
+            &'a self,
+            _function: &'a FunctionRef,
+            _arguments: Value,
+        ) -> BoxFuture<'a, Result<Value, String>> {
+            Box::pin(async { Err("plugin called during replay".to_string()) })
+        }
+    }
+
+    fn program() -> Arc<Program> {
+        Arc::new(Program {
+            instructions: vec![
+                instruction("start", Op::Entry(EntryKind::Start), vec![], vec![1]),
+                instruction(
+                    "fetch",
+                    Op::Call {
+                        function: 0,
+                        arguments: None,
+                        pure: false,
+                    },
+                    vec![0],
+                    vec![],
+                ),
+            ],
+            functions: vec![FunctionRef {
+                plugin: "http-module".into(),
+                function: "fetch".into(),
+            }],
+            entry_points: vec![0],
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn replay_should_answer_plugin_calls_from_the_recording() {
+        let replay = Arc::new(Replay::new());
+        replay.record(vec![], "fetch", Ok(json!({ "status": 200 }).into()));
+
+        let output = Interpreter::new(program(), Arc::new(Unreachable))
+            .with_replay(replay)
+            .run(Value::Null)
+            .await
+            .unwrap();
+
+        assert_eq!(output.outputs["fetch"], json!({ "status": 200 }).into());
+    }
+
+    #[tokio::test]
+    async fn replay_should_reproduce_recorded_failures() {
+        let failure = RuntimeError::at(
+            "fetch",
+            RuntimeErrorKind::FunctionFailed {
+                message: "connection refused".into(),
+            },
+        );
+        let replay = Arc::new(Replay::new());
+        replay.record(vec![], "fetch", Err(failure.clone()));
+
+        let error = Interpreter::new(program(), Arc::new(Unreachable))
+            .with_replay(replay)
+            .run(Value::Null)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error, failure);
+    }
+
+    #[tokio::test]
+    async fn replay_should_report_recorded_timeouts_as_timeouts() {
+        let timeout = RuntimeError::at("fetch", RuntimeErrorKind::TimedOut { timeout_ms: 500 });
+        let replay = Arc::new(Replay::new());
+        replay.record(vec![], "fetch", Err(timeout.clone()));
+
+        let recorder = Arc::new(Recorder::default());
+        let error = Interpreter::new(program(), Arc::new(Unreachable))
+            .with_replay(replay)
+            .with_observer(recorder.clone())
+            .run(Value::Null)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error, timeout);
+        assert!(recorder.events.lock().unwrap().iter().any(|event| matches!(
+            event,
+            ExecutionEvent::NodeTimedOut { node_id, timeout_ms: 500, .. } if node_id == "fetch"
+        )));
+    }
+
+    #[tokio::test]
+    async fn replay_should_fail_calls_missing_from_the_recording() {
+        let error = Interpreter::new(program(), Arc::new(Unreachable))
+            .with_replay(Arc::new(Replay::new()))
+            .run(Value::Null)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind, RuntimeErrorKind::NotRecorded);
+    }
+}
@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{RuntimeError, RuntimeErrorKind};
+
+/// How a failing node is retried, declared in the node `data` under `retry`.
+///
+/// ```json
+/// { "maxAttempts": 3, "backoff": { "kind": "exponential", "delayMs": 200 }, "jitter": 0.2, "retryOn": ["timedOut"] }
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first one.
+    pub max_attempts: u32,
+    /// Wait between attempts. Retries right away when absent.
+    #[serde(default)]
+    pub backoff: Option<Backoff>,
+    /// Spreads every delay randomly by up to this fraction in either
+    /// direction, so nodes failing together don't retry together.
+    #[serde(default)]
+    pub jitter: f64,
+    /// Error codes to retry, see [`RuntimeErrorKind::code`]. Empty retries any error.
+    #[serde(default)]
+    pub retry_on: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Backoff {
+    /// Waits `delay_ms` before every retry.
+    Fixed { delay_ms: u64 },
+    /// Waits `delay_ms` before the first retry, multiplying the delay by
+    /// `factor` for every retry after it, up to `max_delay_ms`.
+    Exponential {
+        delay_ms: u64,
+        #[serde(default = "default_factor")]
+        factor: f64,
+        #[serde(default)]
+        max_delay_ms: Option<u64>,
+    },
+}
+
+fn default_factor() -> f64 {
+    2.0
+}
+
+impl RetryPolicy {
+    /// Whether attempt number `attempt`, which failed with `error`, is followed by another.
+    ///
+    /// Cancelled runs are never retried.
+    pub fn retries(&self, attempt: u32, error: &RuntimeError) -> bool {
+        attempt < self.max_attempts
+            && error.kind != RuntimeErrorKind::Cancelled
+            && (self.retry_on.is_empty() || self.retry_on.iter().any(|c| c == error.kind.code()))
+    }
+
+    /// How long to wait after attempt number `attempt` failed, jitter included.
+    /// Delays too long to represent are capped at [`Duration::MAX`].
+    pub fn delay_after(&self, attempt: u32) -> Duration {
+        let delay_ms = match &self.backoff {
+            None => 0.0,
+            Some(Backoff::Fixed { delay_ms }) => *delay_ms as f64,
+            Some(Backoff::Exponential {
+                delay_ms,
+                factor,
+                max_delay_ms,
+            }) => {
+                let delay = *delay_ms as f64 * factor.powi(attempt.saturating_sub(1) as i32);
+                max_delay_ms.map_or(delay, |max| delay.min(max as f64))
+            }
+        };
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let spread = 1.0 + jitter * (fastrand::f64() * 2.0 - 1.0);
+
+        Duration::try_from_secs_f64((delay_ms * spread).max(0.0) / 1000.0).unwrap_or(Duration::MAX)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use crate::{
+        error::{RuntimeError, RuntimeErrorKind},
+        retry::RetryPolicy,
+    };
+
+    fn policy(policy: serde_json::Value) -> RetryPolicy {
+        serde_json::from_value(policy).unwrap()
+    }
+
+    #[test]
+    fn exponential_backoff_should_grow_up_to_its_maximum() {
+        let policy = policy(json!({
+            "maxAttempts": 5,
+            "backoff": { "kind": "exponential", "delayMs": 100, "maxDelayMs": 300 }
+        }));
+
+        let delays = (1..=4).map(|a| policy.delay_after(a)).collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [100, 200, 300, 300].map(Duration::from_millis).to_vec()
+        );
+    }
+
+    #[test]
+    fn overflowing_backoff_should_wait_as_long_as_possible() {
+        let policy = policy(json!({
+            "maxAttempts": 3,
+            "backoff": { "kind": "exponential", "delayMs": 1000, "factor": 1e300 }
+        }));
+
+        assert_eq!(policy.delay_after(1), Duration::from_secs(1));
+        assert_eq!(policy.delay_after(2), Duration::MAX);
+        assert_eq!(policy.delay_after(3), Duration::MAX);
+    }
+
+    #[test]
+    fn jitter_should_stay_within_its_fraction() {
+        let policy = policy(json!({
+            "maxAttempts": 2,
+            "backoff": { "kind": "fixed", "delayMs": 100 },
+            "jitter": 0.5
+        }));
+
+        for _ in 0..100 {
+            let delay = policy.delay_after(1);
+            assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(150));
+        }
+    }
+
+    #[test]
+    fn retries_should_respect_attempts_and_error_codes() {
+        let policy = policy(json!({ "maxAttempts": 3, "retryOn": ["timedOut"] }));
+        let timed_out = RuntimeError::new(RuntimeErrorKind::TimedOut { timeout_ms: 10 });
+        let failed = RuntimeError::new(RuntimeErrorKind::FunctionFailed {
+            message: "404".into(),
+        });
+
+        assert!(policy.retries(1, &timed_out));
+        assert!(policy.retries(2, &timed_out));
+        assert!(!policy.retries(3, &timed_out));
+        assert!(!policy.retries(1, &failed));
+        assert!(!policy.retries(1, &RuntimeError::new(RuntimeErrorKind::Cancelled)));
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::{HashMap, VecDeque};
+
+use flow_rt_shared::schemas::FlowGraph;
+use serde::Serialize;
+
+use crate::{
+    error::{CompileError, CompileErrorKind},
+    ir::node_types,
+};
+
+/// The dependencies between the nodes of a `FlowGraph`, derived from its edges.
+///
+/// Nodes are addressed by their index in `FlowGraph::nodes`.
+#[derive(Clone, Debug, Default)]
+pub struct DependencyGraph {
+    pub node_ids: Vec<String>,
+    pub successors: Vec<Vec<usize>>,
+    pub predecessors: Vec<Vec<usize>>,
+}
+
+/// A valid execution order for a [`DependencyGraph`].
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct Schedule {
+    /// Every node, placed after all of the nodes it depends on.
+    pub order: Vec<usize>,
+    /// Nodes that can't be reached from any root and will therefore never run.
+    pub unreachable: Vec<usize>,
+}
+
+/// Builds the dependency graph of `graph` and schedules it from its entry nodes.
+pub fn schedule_flow_graph(graph: &FlowGraph) -> Result<(DependencyGraph, Schedule), CompileError> {
+    let dependencies = DependencyGraph::from_flow_graph(graph)?;
+    let roots = graph
+        .nodes
+        .iter()
+        .flatten()
+        .enumerate()
+        .filter(|(_, node)| node_types::is_entry(&node.node_type))
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+
+    if roots.is_empty() {
+        return Err(CompileError::new(CompileErrorKind::MissingEntryPoint));
+    }
+
+    let schedule = dependencies.schedule(&roots)?;
+    Ok((dependencies, schedule))
+}
+
+impl DependencyGraph {
+    pub fn from_flow_graph(graph: &FlowGraph) -> Result<Self, CompileError> {
+        let nodes = graph.nodes.as_deref().unwrap_or_default();
+        let edges = graph.edges.as_deref().unwrap_or_default();
+
+        let mut index_by_id = HashMap::with_capacity(nodes.len());
+        for (index, node) in nodes.iter().enumerate() {
+            if index_by_id.insert(node.id.as_str(), index).is_some() {
+                return Err(CompileError::at(&node.id, CompileErrorKind::DuplicateNode));
+            }
+        }
+
+        let mut dependencies = Self {
+            node_ids: nodes.iter().map(|n| n.id.clone()).collect(),
+            successors: vec![vec![]; nodes.len()],
+            predecessors: vec![vec![]; nodes.len()],
+        };
+
+        for edge in edges {
+            let dangling = |id: &String| {
+                CompileError::at(
+                    id,
+                    CompileErrorKind::DanglingEdge {
+                        edge_id: edge.id.clone(),
+                    },
+                )
+            };
+
+            let source = *index_by_id
+                .get(edge.source.as_str())
+                .ok_or_else(|| dangling(&edge.source))?;
+            let target = *index_by_id
+                .get(edge.target.as_str())
+                .ok_or_else(|| dangling(&edge.target))?;
+
+            dependencies.successors[source].push(target);
+            dependencies.predecessors[target].push(source);
+        }
+
+        Ok(dependencies)
+    }
+
+    /// Maps every node id to its index, for lookups while walking the edges.
+    pub fn index_by_id(&self) -> HashMap<&str, usize> {
+        self.node_ids
+            .iter()
+            .enumerate()
+            .map(|(index, id)| (id.as_str(), index))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.node_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.node_ids.is_empty()
+    }
+
+    /// Orders the nodes topologically, keeping the original node order where the
+    /// graph leaves a choice, and marks every node not reachable from `roots`.
+    pub fn schedule(&self, roots: &[usize]) -> Result<Schedule, CompileError> {
+        if let Some(cycle) = self.find_cycle() {
+            return Err(CompileError::at(
+                cycle[0].clone(),
+                CompileErrorKind::Cycle { path: cycle },
+            ));
+        }
+
+        let mut in_degree = self.predecessors.iter().map(Vec::len).collect::<Vec<_>>();
+        let mut ready = (0..self.len())
+            .filter(|i| in_degree[*i] == 0)
+            .collect::<VecDeque<_>>();
+
+        let mut order = Vec::with_capacity(self.len());
+        while let Some(current) = ready.pop_front() {
+            order.push(current);
+            for next in &self.successors[current] {
+                in_degree[*next] -= 1;
+                if in_degree[*next] == 0 {
+                    ready.push_back(*next);
+                }
+            }
+        }
+
+        let reachable = self.reachable_from(roots);
+        let unreachable = (0..self.len()).filter(|i| !reachable[*i]).collect();
+
+        Ok(Schedule { order, unreachable })
+    }
+
+    fn reachable_from(&self, roots: &[usize]) -> Vec<bool> {
+        let mut reachable = vec![false; self.len()];
+        let mut pending = roots.to_vec();
+
+        while let Some(current) = pending.pop() {
+            if std::mem::replace(&mut reachable[current], true) {
+                continue;
+            }
+            pending.extend(self.successors[current].iter().copied());
+        }
+
+        reachable
+    }
+
+    /// Returns the node ids along the first cycle found, starting and ending at
+    /// the same node.
+    pub fn find_cycle(&self) -> Option<Vec<String>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            Unvisited,
+            InProgress,
+            Done,
+        }
+
+        let mut marks = vec![Mark::Unvisited; self.len()];
+
+        for root in 0..self.len() {
+            if marks[root] != Mark::Unvisited {
+                continue;
+            }
+
+            // Iterative DFS; each frame is (node, index of the next successor to visit).
+            let mut path = vec![(root, 0usize)];
+            marks[root] = Mark::InProgress;
+
+            while let Some((current, next_child)) = path.last_mut() {
+                let current = *current;
+                let Some(child) = self.successors[current].get(*next_child).copied() else {
+                    marks[current] = Mark::Done;
+                    path.pop();
+                    continue;
+                };
+                *next_child += 1;
+
+                match marks[child] {
+                    Mark::Unvisited => {
+                        marks[child] = Mark::InProgress;
+                        path.push((child, 0));
+                    }
+                    Mark::InProgress => {
+                        let start = path.iter().position(|(n, _)| *n == child).unwrap_or(0);
+                        let mut cycle = path[start..]
+                            .iter()
+                            .map(|(n, _)| self.node_ids[*n].clone())
+                            .collect::<Vec<_>>();
+                        cycle.push(self.node_ids[child].clone());
+                        return Some(cycle);
+                    }
+                    Mark::Done => {}
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::scheduler::DependencyGraph;
+
+    fn graph(size: usize, edges: &[(usize, usize)]) -> DependencyGraph {
+        let mut graph = DependencyGraph {
+            node_ids: (0..size).map(|i| format!("n{i}")).collect(),
+            successors: vec![vec![]; size],
+            predecessors: vec![vec![]; size],
+        };
+
+        for (source, target) in edges {
+            graph.successors[*source].push(*target);
+            graph.predecessors[*target].push(*source);
+        }
+
+        graph
+    }
+
+    #[test]
+    fn find_cycle_should_name_the_loop() {
+        let graph = graph(4, &[(0, 1), (1, 2), (2, 3), (3, 1)]);
+
+        assert_eq!(
+            graph.find_cycle(),
+            Some(vec!["n1".into(), "n2".into(), "n3".into(), "n1".into()])
+        );
+    }
+
+    #[test]
+    fn schedule_should_flag_disconnected_nodes() {
+        let graph = graph(5, &[(0, 1), (1, 2), (3, 4)]);
+        let schedule = graph.schedule(&[0]).unwrap();
+
+        assert_eq!(schedule.order.len(), 5);
+        assert_eq!(schedule.unreachable, vec![3, 4]);
+    }
+}
@@ -0,0 +1,522 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use flow_rt_shared::value::Value;
+use rhai::{
+    AST, Dynamic, Engine, EvalAltResult, Position, Scope, module_resolvers::DummyModuleResolver,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cancellation::Cancellation,
+    interpreter::FunctionHost,
+    ir::{FunctionRef, Script},
+};
+
+/// Extension of script files, added to script names that don't have one.
+pub const SCRIPT_EXTENSION: &str = "rhai";
+
+/// Resolves the scripts referenced by `scriptNode`s while compiling a graph.
+pub trait ScriptLoader {
+    fn load(&self, name: &str) -> Result<String, String>;
+}
+
+/// Scripts by name, for embedders and tests that keep them in memory.
+impl ScriptLoader for BTreeMap<String, String> {
+    fn load(&self, name: &str) -> Result<String, String> {
+        self.get(name)
+            .cloned()
+            .ok_or_else(|| "no script with that name".to_string())
+    }
+}
+
+/// Reads scripts from a directory, usually the `scripts` directory of a project.
+///
+/// Names are relative to the directory and can't leave it.
+pub struct ScriptDirectory {
+    root: PathBuf,
+}
+
+impl ScriptDirectory {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The scripts of the project at `project`.
+    pub fn of_project(project: &Path) -> Self {
+        Self::new(project.join("scripts"))
+    }
+}
+
+impl ScriptLoader for ScriptDirectory {
+    fn load(&self, name: &str) -> Result<String, String> {
+        let mut relative = PathBuf::from(name);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err("script names must be relative to the scripts directory".into());
+        }
+        if relative.extension().is_none() {
+            relative.set_extension(SCRIPT_EXTENSION);
+        }
+
+        let location = self.root.join(relative);
+        std::fs::read_to_string(&location)
+            .map_err(|e| format!("{location:?} could not be read: {e}"))
+    }
+}
+
+/// A script that failed to compile or run, at the position it failed at when known.
+///
+/// Lines and columns start at 1.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ScriptError {
+    pub message: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+impl ScriptError {
+    fn new(message: impl Display, position: Position) -> Self {
+        Self {
+            message: message.to_string(),
+            line: position.line().map(|line| line as u32),
+            column: position.position().map(|column| column as u32),
+        }
+    }
+}
+
+impl From<Box<EvalAltResult>> for ScriptError {
+    fn from(mut error: Box<EvalAltResult>) -> Self {
+        // Taken out so the message doesn't repeat it.
+        let position = error.take_position();
+        match *error {
+            // Raised by the sandbox, with the reason as its token.
+            EvalAltResult::ErrorTerminated(reason, _) => Self::new(reason, position),
+            error => Self::new(error, position),
+        }
+    }
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "{} (line {line}, column {column})", self.message)
+            }
+            (Some(line), None) => write!(f, "{} (line {line})", self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// What scripts may do, read from the `[scripts]` table of `Flow.toml` or
+/// the app settings. A limit of 0 disables it.
+///
+/// Keys are camelCase like the rest of the settings; `Flow.toml` may spell
+/// them in snake_case like its other tables.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ScriptSandbox {
+    /// Operations a single script run may perform, roughly one per expression evaluated.
+    #[serde(alias = "max_operations")]
+    pub max_operations: u64,
+    /// How deep script functions may call each other.
+    #[serde(alias = "max_call_depth")]
+    pub max_call_depth: usize,
+    /// Longest string a script may build, in bytes.
+    #[serde(alias = "max_string_size")]
+    pub max_string_size: usize,
+    #[serde(alias = "max_array_size")]
+    pub max_array_size: usize,
+    #[serde(alias = "max_map_size")]
+    pub max_map_size: usize,
+    /// How long a single script run may take.
+    #[serde(alias = "timeout_ms")]
+    pub timeout_ms: u64,
+    /// Plugin functions scripts may call through `call_plugin`, as `plugin::function`.
+    #[serde(alias = "allowed_functions")]
+    pub allowed_functions: Vec<String>,
+}
+
+impl Default for ScriptSandbox {
+    fn default() -> Self {
+        Self {
+            max_operations: 10_000_000,
+            max_call_depth: 64,
+            max_string_size: 1024 * 1024,
+            max_array_size: 100_000,
+            max_map_size: 100_000,
+            timeout_ms: 5_000,
+            allowed_functions: vec![],
+        }
+    }
+}
+
+impl ScriptSandbox {
+    pub fn allows(&self, function: &FunctionRef) -> bool {
+        self.allowed_functions
+            .iter()
+            .any(|allowed| *allowed == format!("{}::{}", function.plugin, function.function))
+    }
+}
+
+/// The script run in progress on this thread.
+struct ScriptRun {
+    started: Instant,
+    /// Where `call_plugin` sends calls; `None` when plugins can't be called in this run.
+    host: Option<Arc<dyn FunctionHost>>,
+    /// Stops the script once cancelled, e.g. when the node running it timed out.
+    cancellation: Option<Arc<Cancellation>>,
+}
+
+impl ScriptRun {
+    /// Why the script has to stop before its next operation, if it has to.
+    fn stop_reason(&self, timeout: Option<Duration>) -> Option<String> {
+        if self.cancellation.as_ref().is_some_and(|c| c.is_cancelled()) {
+            return Some("script was stopped as its node was cancelled".into());
+        }
+        timeout
+            .filter(|timeout| self.started.elapsed() > *timeout)
+            .map(|timeout| format!("script ran longer than {}ms", timeout.as_millis()))
+    }
+}
+
+thread_local! {
+    static CURRENT_RUN: RefCell<Option<ScriptRun>> = const { RefCell::new(None) };
+}
+
+/// Compiles and runs the scripts of `scriptNode`s within a [`ScriptSandbox`].
+///
+/// Each script node runs its script with the node's input bound to `input`,
+/// and every field of an object input bound to a variable of the same name.
+/// The value of the last expression becomes the output of the node.
+///
+/// Scripts can't import modules or print. The only way out of the sandbox
+/// is `call_plugin(plugin, function, arguments)`, for the functions the
+/// sandbox allows.
+pub struct ScriptEngine {
+    engine: Engine,
+    sandbox: ScriptSandbox,
+    /// The last compiled version of every script, by name, along with the
+    /// blake3 hash of its source. Editing a script replaces its entry.
+    compiled: Mutex<HashMap<String, (blake3::Hash, Arc<AST>)>>,
+}
+
+impl Default for ScriptEngine {
+    fn default() -> Self {
+        Self::new(ScriptSandbox::default())
+    }
+}
+
+impl ScriptEngine {
+    pub fn new(sandbox: ScriptSandbox) -> Self {
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(sandbox.max_operations)
+            .set_max_call_levels(sandbox.max_call_depth)
+            .set_max_string_size(sandbox.max_string_size)
+            .set_max_array_size(sandbox.max_array_size)
+            .set_max_map_size(sandbox.max_map_size)
+            .set_module_resolver(DummyModuleResolver::new())
+            .on_print(|_| {})
+            .on_debug(|_, _, _| {});
+
+        let timeout = (sandbox.timeout_ms > 0).then(|| Duration::from_millis(sandbox.timeout_ms));
+        engine.on_progress(move |_| {
+            CURRENT_RUN
+                .with_borrow(|run| run.as_ref().and_then(|run| run.stop_reason(timeout)))
+                .map(Dynamic::from)
+        });
+
+        let allowed = sandbox.clone();
+        engine.register_fn(
+            "call_plugin",
+            move |plugin: &str, function: &str, arguments: Dynamic| {
+                call_plugin(&allowed, plugin, function, arguments)
+            },
+        );
+
+        Self {
+            engine,
+            sandbox,
+            compiled: Mutex::default(),
+        }
+    }
+
+    pub fn sandbox(&self) -> &ScriptSandbox {
+        &self.sandbox
+    }
+
+    /// The engine used by interpreters that aren't given one.
+    pub fn shared() -> Arc<ScriptEngine> {
+        static SHARED: OnceLock<Arc<ScriptEngine>> = OnceLock::new();
+        SHARED.get_or_init(Arc::default).clone()
+    }
+
+    /// Compiles `source`, reporting where it is malformed.
+    pub fn compile(&self, source: &str) -> Result<AST, ScriptError> {
+        self.engine
+            .compile(source)
+            .map_err(|e| ScriptError::new(e.err_type(), e.position()))
+    }
+
+    fn compiled(&self, script: &Script) -> Result<Arc<AST>, ScriptError> {
+        let hash = blake3::hash(script.source.as_bytes());
+        let mut compiled = self.compiled.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some((compiled_hash, ast)) = compiled.get(&script.name)
+            && *compiled_hash == hash
+        {
+            return Ok(ast.clone());
+        }
+
+        let ast = Arc::new(self.compile(&script.source)?);
+        compiled.insert(script.name.clone(), (hash, ast.clone()));
+        Ok(ast)
+    }
+
+    /// Runs `script` with `input` and converts the value it evaluates to.
+    /// Allowed plugin functions are called through `host`.
+    ///
+    /// Blocks until the script finishes, so async callers should run it on a
+    /// blocking thread of their tokio runtime.
+    pub fn run(
+        &self,
+        script: &Script,
+        input: Value,
+        host: Option<Arc<dyn FunctionHost>>,
+    ) -> Result<Value, ScriptError> {
+        self.run_until_cancelled(script, input, host, None)
+    }
+
+    /// Like [`Self::run`], stopping the script at its next operation once
+    /// `cancellation` is cancelled.
+    pub fn run_until_cancelled(
+        &self,
+        script: &Script,
+        input: Value,
+        host: Option<Arc<dyn FunctionHost>>,
+        cancellation: Option<Arc<Cancellation>>,
+    ) -> Result<Value, ScriptError> {
+        let ast = self.compiled(script)?;
+
+        CURRENT_RUN.set(Some(ScriptRun {
+            started: Instant::now(),
+            host,
+            cancellation,
+        }));
+        let result = self.evaluate(&ast, input);
+        CURRENT_RUN.set(None);
+
+        result
+    }
+
+    fn evaluate(&self, ast: &AST, input: Value) -> Result<Value, ScriptError> {
+        let mut scope = Scope::new();
+        scope.push_dynamic("input", rhai::serde::to_dynamic(&input)?);
+        if let Value::Object(fields) = &input {
+            for (name, value) in fields {
+                scope.push_dynamic(name.as_str(), rhai::serde::to_dynamic(value)?);
+            }
+        }
+
+        let output = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut scope, ast)?;
+        Ok(rhai::serde::from_dynamic(&output)?)
+    }
+}
+
+fn call_plugin(
+    sandbox: &ScriptSandbox,
+    plugin: &str,
+    function: &str,
+    arguments: Dynamic,
+) -> Result<Dynamic, Box<EvalAltResult>> {
+    let qualified = format!("{plugin}::{function}");
+    let function = FunctionRef {
+        plugin: plugin.to_string(),
+        function: function.to_string(),
+    };
+    if !sandbox.allows(&function) {
+        return Err(format!("{qualified} is not allowed in scripts").into());
+    }
+
+    let host = CURRENT_RUN
+        .with_borrow(|run| {
+            run.as_ref()
+                .filter(|run| !run.cancellation.as_ref().is_some_and(|c| c.is_cancelled()))
+                .and_then(|run| run.host.clone())
+        })
+        .ok_or("plugins can't be called from scripts in this run")?;
+    let runtime = tokio::runtime::Handle::try_current()
+        .map_err(|_| "plugins can only be called from scripts run by the interpreter")?;
+
+    let arguments = rhai::serde::from_dynamic::<Value>(&arguments)?;
+    let output = runtime
+        .block_on(host.call(&function, arguments))
+        .map_err(|message| format!("{qualified} failed: {message}"))?;
+    rhai::serde::to_dynamic(&output)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use flow_rt_shared::value::Value;
+    use serde_json::json;
+
+    use crate::{
+        cancellation::Cancellation,
+        ir::Script,
+        script::{ScriptEngine, ScriptSandbox},
+    };
+
+    fn script(source: &str) -> Script {
+        Script {
+            name: "test.rhai".into(),
+            source: source.into(),
+        }
+    }
+
+    #[test]
+    fn scripts_should_see_their_inputs_as_variables() {
+        let output = ScriptEngine::default()
+            .run(
+                &script("#{ sum: a + b, count: input.len() }"),
+                json!({ "a": 1, "b": 2 }).into(),
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(output, Value::from(json!({ "sum": 3, "count": 2 })));
+    }
+
+    #[test]
+    fn sandbox_should_read_settings_and_flow_toml_keys() {
+        let settings: ScriptSandbox =
+            serde_json::from_value(json!({ "maxOperations": 5, "timeoutMs": 10 })).unwrap();
+        let flow_toml: ScriptSandbox =
+            serde_json::from_value(json!({ "max_operations": 5, "timeout_ms": 10 })).unwrap();
+
+        assert_eq!((settings.max_operations, settings.timeout_ms), (5, 10));
+        assert_eq!(settings, flow_toml);
+    }
+
+    #[test]
+    fn edited_scripts_should_replace_their_compiled_version() {
+        let engine = ScriptEngine::default();
+        for source in ["1", "2", "3"] {
+            let output = engine.run(&script(source), Value::Null, None).unwrap();
+            assert_eq!(output, Value::from(source.parse::<i64>().unwrap()));
+        }
+
+        assert_eq!(engine.compiled.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn compile_errors_should_have_a_position() {
+        let error = ScriptEngine::default()
+            .compile("let a = 1;\nlet b = ;")
+            .unwrap_err();
+
+        assert_eq!((error.line, error.column), (Some(2), Some(9)));
+    }
+
+    #[test]
+    fn runtime_errors_should_have_a_position() {
+        let error = ScriptEngine::default()
+            .run(&script("let a = 1;\n  a + missing"), Value::Null, None)
+            .unwrap_err();
+
+        assert_eq!(error.line, Some(2));
+        assert!(error.message.contains("missing"), "{}", error.message);
+    }
+
+    #[test]
+    fn scripts_should_stop_at_their_operation_limit() {
+        let engine = ScriptEngine::new(ScriptSandbox {
+            max_operations: 1_000,
+            ..Default::default()
+        });
+        let error = engine
+            .run(&script("loop {}"), Value::Null, None)
+            .unwrap_err();
+
+        assert!(error.message.contains("operations"), "{}", error.message);
+    }
+
+    #[test]
+    fn scripts_should_stop_at_their_timeout() {
+        let engine = ScriptEngine::new(ScriptSandbox {
+            max_operations: 0,
+            timeout_ms: 20,
+            ..Default::default()
+        });
+        let error = engine
+            .run(&script("loop {}"), Value::Null, None)
+            .unwrap_err();
+
+        assert!(error.message.contains("20ms"), "{}", error.message);
+    }
+
+    #[test]
+    fn scripts_should_stop_once_cancelled() {
+        let engine = ScriptEngine::new(ScriptSandbox {
+            max_operations: 0,
+            timeout_ms: 0,
+            ..Default::default()
+        });
+        let cancellation = Arc::new(Cancellation::new());
+        cancellation.cancel();
+
+        let error = engine
+            .run_until_cancelled(&script("loop {}"), Value::Null, None, Some(cancellation))
+            .unwrap_err();
+
+        assert!(error.message.contains("cancelled"), "{}", error.message);
+    }
+
+    #[test]
+    fn scripts_should_only_call_allowed_plugin_functions() {
+        let engine = ScriptEngine::new(ScriptSandbox {
+            allowed_functions: vec!["fs::read".into()],
+            ..Default::default()
+        });
+
+        let error = engine
+            .run(
+                &script(r#"call_plugin("fs", "write", "/etc/passwd")"#),
+                Value::Null,
+                None,
+            )
+            .unwrap_err();
+        assert!(error.message.contains("not allowed"), "{}", error.message);
+
+        let error = engine
+            .run(
+                &script(r#"call_plugin("fs", "read", "/etc/passwd")"#),
+                Value::Null,
+                None,
+            )
+            .unwrap_err();
+        assert!(
+            error.message.contains("can't be called"),
+            "{}",
+            error.message
+        );
+
+        let error = engine
+            .run(&script(r#"import "secrets" as s; 1"#), Value::Null, None)
+            .unwrap_err();
+        assert!(error.message.contains("secrets"), "{}", error.message);
+    }
+}
@@ -0,0 +1,330 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+};
+
+use flow_rt_shared::value::Value;
+
+use crate::{error::RuntimeError, observer::ExecutionEvent, references::resolve_references};
+
+/// What secret values are replaced with in everything a run reports.
+pub const REDACTED: &str = "********";
+
+const SECRET_NAMESPACE: &str = "secret";
+
+/// Where the secrets referenced by `${secret.NAME}` in node arguments come from.
+///
+/// `flow-rt-app` implements this on top of the encrypted secrets store of the
+/// open project.
+pub trait SecretStore: Send + Sync {
+    fn secret(&self, name: &str) -> Option<String>;
+}
+
+/// Secrets by name, for embedders that load them up front.
+impl SecretStore for BTreeMap<String, String> {
+    fn secret(&self, name: &str) -> Option<String> {
+        self.get(name).cloned()
+    }
+}
+
+/// Resolves secret references for the runs of an interpreter, and redacts
+/// the secrets it resolved from the values and errors those runs report.
+pub struct Secrets {
+    store: Arc<dyn SecretStore>,
+    revealed: Mutex<BTreeSet<String>>,
+}
+
+/// No secrets at all: every reference fails to resolve.
+impl Default for Secrets {
+    fn default() -> Self {
+        Self::new(Arc::new(BTreeMap::new()))
+    }
+}
+
+impl Secrets {
+    pub fn new(store: Arc<dyn SecretStore>) -> Self {
+        Self {
+            store,
+            revealed: Mutex::default(),
+        }
+    }
+
+    /// Replaces every `${secret.NAME}` in the strings of `value` with the
+    /// secret `NAME`. Fails with the name of the first secret the store
+    /// doesn't hold.
+    pub(crate) fn resolve(&self, value: &Value) -> Result<Value, String> {
+        resolve_references(value, SECRET_NAMESPACE, |name| {
+            let secret = self.store.secret(name)?;
+            self.reveal(&secret);
+            Some(secret)
+        })
+    }
+
+    fn reveal(&self, secret: &str) {
+        if !secret.is_empty() {
+            self.lock().insert(secret.to_string());
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeSet<String>> {
+        self.revealed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// `value` with every secret resolved so far replaced by [`REDACTED`].
+    pub(crate) fn redact(&self, value: Value) -> Value {
+        let revealed = self.lock();
+        if revealed.is_empty() {
+            return value;
+        }
+        // Longest first, so a secret containing a shorter one is replaced whole.
+        let mut secrets = revealed.iter().map(String::as_str).collect::<Vec<_>>();
+        secrets.sort_by_key(|secret| Reverse(secret.len()));
+        redact_value(value, &secrets)
+    }
+
+    /// Whether a secret resolved so far appears in the strings or bytes of `value`.
+    pub(crate) fn appear_in(&self, value: &Value) -> bool {
+        let revealed = self.lock();
+        !revealed.is_empty() && contains_secret(value, &revealed)
+    }
+
+    pub(crate) fn redact_error(&self, error: RuntimeError) -> RuntimeError {
+        if self.lock().is_empty() {
+            return error;
+        }
+        // Errors carry messages in many shapes; going through their
+        // serialized form catches every one of them.
+        serde_json::to_value(&error)
+            .map(|serialized| self.redact(serialized.into()))
+            .and_then(|redacted| serde_json::from_value(redacted.into()))
+            .unwrap_or(error)
+    }
+
+    pub(crate) fn redact_event(&self, event: ExecutionEvent) -> ExecutionEvent {
+        match event {
+            ExecutionEvent::NodeStarted {
+                node_id,
+                call_stack,
+                input,
+            } => ExecutionEvent::NodeStarted {
+                node_id,
+                call_stack,
+                input: self.redact(input),
+            },
+            ExecutionEvent::NodeFinished {
+                node_id,
+                call_stack,
+                output,
+                duration_ms,
+            } => ExecutionEvent::NodeFinished {
+                node_id,
+                call_stack,
+                output: self.redact(output),
+                duration_ms,
+            },
+            ExecutionEvent::NodeFailed {
+                node_id,
+                call_stack,
+                error,
+                duration_ms,
+            } => ExecutionEvent::NodeFailed {
+                node_id,
+                call_stack,
+                error: self.redact_error(error),
+                duration_ms,
+            },
+            ExecutionEvent::NodeRetrying {
+                node_id,
+                call_stack,
+                attempt,
+                error,
+                delay_ms,
+            } => ExecutionEvent::NodeRetrying {
+                node_id,
+                call_stack,
+                attempt,
+                error: self.redact_error(error),
+                delay_ms,
+            },
+            ExecutionEvent::RunFinished { error, duration_ms } => ExecutionEvent::RunFinished {
+                error: error.map(|error| self.redact_error(error)),
+                duration_ms,
+            },
+            other => other,
+        }
+    }
+}
+
+fn contains_secret(value: &Value, revealed: &BTreeSet<String>) -> bool {
+    match value {
+        Value::String(text) => revealed.iter().any(|secret| text.contains(secret.as_str())),
+        Value::Bytes(bytes) => revealed
+            .iter()
+            .any(|secret| find(bytes, secret.as_bytes()).is_some()),
+        Value::Array(values) => values.iter().any(|value| contains_secret(value, revealed)),
+        Value::Object(fields) => fields
+            .values()
+            .any(|value| contains_secret(value, revealed)),
+        _ => false,
+    }
+}
+
+fn redact_value(value: Value, revealed: &[&str]) -> Value {
+    match value {
+        Value::String(mut text) => {
+            for secret in revealed {
+                if text.contains(secret) {
+                    text = text.replace(secret, REDACTED);
+                }
+            }
+            Value::String(text)
+        }
+        Value::Bytes(mut bytes) => {
+            for secret in revealed {
+                if find(&bytes, secret.as_bytes()).is_some() {
+                    bytes = replace(&bytes, secret.as_bytes(), REDACTED.as_bytes());
+                }
+            }
+            Value::Bytes(bytes)
+        }
+        Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(|value| redact_value(value, revealed))
+                .collect(),
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key, redact_value(value, revealed)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// Index of the first occurrence of `needle` in `bytes`. Bytes holding text
+/// are searched like strings, without requiring the rest to be UTF-8.
+fn find(bytes: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+    bytes
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn replace(bytes: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+    let mut replaced = Vec::with_capacity(bytes.len());
+    let mut rest = bytes;
+    while let Some(index) = find(rest, from) {
+        replaced.extend_from_slice(&rest[..index]);
+        replaced.extend_from_slice(to);
+        rest = &rest[index + from.len()..];
+    }
+    replaced.extend_from_slice(rest);
+    replaced
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeMap, sync::Arc};
+
+    use flow_rt_shared::value::Value;
+    use serde_json::json;
+
+    use crate::{
+        error::{RuntimeError, RuntimeErrorKind},
+        secrets::{REDACTED, Secrets},
+    };
+
+    fn secrets() -> Secrets {
+        Secrets::new(Arc::new(BTreeMap::from([(
+            "TOKEN".to_string(),
+            "s3cr3t".to_string(),
+        )])))
+    }
+
+    #[test]
+    fn references_should_resolve_to_their_secret() {
+        let secrets = secrets();
+        let arguments = json!({
+            "headers": { "authorization": "Bearer ${secret.TOKEN}" },
+            "retries": 3
+        });
+
+        let resolved = secrets.resolve(&arguments.into()).unwrap();
+
+        assert_eq!(
+            resolved,
+            json!({
+                "headers": { "authorization": "Bearer s3cr3t" },
+                "retries": 3
+            })
+            .into()
+        );
+        assert_eq!(
+            secrets.resolve(&json!("${secret.MISSING}").into()),
+            Err("MISSING".to_string())
+        );
+    }
+
+    #[test]
+    fn resolved_secrets_should_be_redacted() {
+        let secrets = secrets();
+        let echoed = Value::from(json!({ "sent": "Bearer s3cr3t" }));
+        assert_eq!(secrets.redact(echoed.clone()), echoed);
+
+        secrets.resolve(&json!("${secret.TOKEN}").into()).unwrap();
+
+        assert_eq!(
+            secrets.redact(echoed),
+            json!({ "sent": format!("Bearer {REDACTED}") }).into()
+        );
+
+        let error = RuntimeError::at(
+            "fetch",
+            RuntimeErrorKind::FunctionFailed {
+                message: "401 for token s3cr3t".into(),
+            },
+        );
+        assert_eq!(
+            secrets.redact_error(error).kind,
+            RuntimeErrorKind::FunctionFailed {
+                message: format!("401 for token {REDACTED}")
+            }
+        );
+    }
+
+    #[test]
+    fn secrets_containing_other_secrets_should_be_redacted_whole() {
+        let secrets = Secrets::new(Arc::new(BTreeMap::from([
+            ("SHORT".to_string(), "abc".to_string()),
+            ("LONG".to_string(), "abcdef".to_string()),
+        ])));
+        secrets
+            .resolve(&json!(["${secret.SHORT}", "${secret.LONG}"]).into())
+            .unwrap();
+
+        assert_eq!(
+            secrets.redact(json!("abcdef and abc").into()),
+            Value::from(format!("{REDACTED} and {REDACTED}"))
+        );
+    }
+
+    #[test]
+    fn secrets_in_bytes_should_be_found_and_redacted() {
+        let secrets = secrets();
+        secrets.resolve(&json!("${secret.TOKEN}").into()).unwrap();
+        let mut body = b"token=s3cr3t&again=s3cr3t".to_vec();
+        body.push(0xff);
+
+        assert!(secrets.appear_in(&Value::Bytes(body.clone())));
+        assert!(!secrets.appear_in(&Value::Bytes(b"token=".to_vec())));
+
+        let mut redacted = format!("token={REDACTED}&again={REDACTED}").into_bytes();
+        redacted.push(0xff);
+        assert_eq!(secrets.redact(Value::Bytes(body)), Value::Bytes(redacted));
+    }
+}
@@ -0,0 +1,172 @@
+//! Builders and hosts shared by the tests of the runtime.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use flow_rt_shared::{
+    schemas::{FlowGraph, FlowGraphEdge, FlowGraphNode, FlowGraphVariable},
+    value::{Value, ValueType},
+};
+use tokio::sync::Notify;
+
+use crate::{
+    compiler::{FunctionCatalog, compile_with_scripts},
+    interpreter::{BoxFuture, FunctionHost},
+    ir::{Binding, FunctionRef, Instruction, Op, Program},
+    observer::{ExecutionEvent, RunObserver},
+};
+
+/// Knows `http-module::fetch` and `test::echo`, without signatures.
+pub struct Catalog;
+
+impl FunctionCatalog for Catalog {
+    fn contains(&self, plugin: &str, function: &str) -> bool {
+        matches!(
+            (plugin, function),
+            ("http-module", "fetch") | ("test", "echo")
+        )
+    }
+}
+
+pub fn node(id: &str, node_type: &str, data: serde_json::Value) -> FlowGraphNode {
+    FlowGraphNode {
+        id: id.to_string(),
+        node_type: node_type.to_string(),
+        data,
+        ..Default::default()
+    }
+}
+
+/// A `foreignFunctionNode` calling `plugin::function`.
+pub fn function_node(id: &str, plugin: &str, function: &str) -> FlowGraphNode {
+    node(
+        id,
+        "foreignFunctionNode",
+        serde_json::json!({ "pluginName": plugin, "functionName": function }),
+    )
+}
+
+pub fn edge(source: &str, target: &str) -> FlowGraphEdge {
+    FlowGraphEdge {
+        id: format!("{source}->{target}"),
+        source: source.to_string(),
+        target: target.to_string(),
+        ..Default::default()
+    }
+}
+
+/// An edge leaving the output port `port` of `source`, such as a branch of an `ifNode`.
+pub fn port_edge(source: &str, port: &str, target: &str) -> FlowGraphEdge {
+    FlowGraphEdge {
+        source_handle: Some(port.to_string()),
+        ..edge(source, target)
+    }
+}
+
+pub fn graph(nodes: Vec<FlowGraphNode>, edges: Vec<FlowGraphEdge>) -> FlowGraph {
+    FlowGraph {
+        nodes: Some(nodes),
+        edges: Some(edges),
+        ..Default::default()
+    }
+}
+
+pub fn variable(
+    name: &str,
+    value_type: ValueType,
+    default: serde_json::Value,
+) -> FlowGraphVariable {
+    FlowGraphVariable {
+        name: name.to_string(),
+        value_type,
+        default,
+    }
+}
+
+/// Compiles `graph` against [`Catalog`], with the sources of its scripts by name.
+pub fn compiled(graph: &FlowGraph, scripts: &[(&str, &str)]) -> Arc<Program> {
+    let scripts = scripts
+        .iter()
+        .map(|(name, source)| (name.to_string(), source.to_string()))
+        .collect::<BTreeMap<_, _>>();
+    Arc::new(compile_with_scripts(graph, &Catalog, &scripts).unwrap())
+}
+
+/// An instruction fed by the whole output of every instruction in `inputs`.
+pub fn instruction(node_id: &str, op: Op, inputs: Vec<usize>, outputs: Vec<usize>) -> Instruction {
+    Instruction {
+        node_id: node_id.into(),
+        op,
+        inputs: inputs
+            .into_iter()
+            .map(|source| Binding {
+                edge_id: format!("{source}->{node_id}"),
+                source,
+                source_port: None,
+                target_port: None,
+            })
+            .collect(),
+        outputs,
+        input_ports: vec![],
+        timeout_ms: None,
+        retry: None,
+    }
+}
+
+/// Records the arguments of every call, so tests can also observe graphs
+/// started in the background. `reject` fails with the arguments in its
+/// message, every other function returns them.
+#[derive(Default)]
+pub struct RecordingHost {
+    pub calls: Mutex<Vec<Value>>,
+    called: Notify,
+}
+
+impl RecordingHost {
+    /// Waits until at least `count` calls were recorded.
+    pub async fn wait_for(&self, count: usize) {
+        let wait = async {
+            while self.calls.lock().unwrap().len() < count {
+                self.called.notified().await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), wait)
+            .await
+            .expect("the expected calls were never made");
+    }
+}
+
+impl FunctionHost for RecordingHost {
+    fn call<'a>(
+        &'a self,
+        function: &'a FunctionRef,
+        arguments: Value,
+    ) -> BoxFuture<'a, Result<Value, String>> {
+        Box::pin(async move {
+            self.calls.lock().unwrap().push(arguments.clone());
+            self.called.notify_one();
+            match function.function.as_str() {
+                "reject" => Err(format!(
+                    "rejected {}",
+                    serde_json::to_string(&arguments).unwrap()
+                )),
+                _ => Ok(arguments),
+            }
+        })
+    }
+}
+
+/// Keeps every event of the runs it observes.
+#[derive(Default)]
+pub struct Recorder {
+    pub events: Mutex<Vec<ExecutionEvent>>,
+}
+
+impl RunObserver for Recorder {
+    fn notify(&self, event: ExecutionEvent) {
+        self.events.lock().unwrap().push(event);
+    }
+}
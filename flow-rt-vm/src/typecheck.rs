@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+
+use flow_rt_shared::{
+    schemas::{FlowGraph, FlowGraphNode, FlowGraphPort},
+    value::{Coercion, ValueType},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{compiler::FunctionCatalog, ir::node_types, scheduler::schedule_flow_graph};
+
+/// Name of the single output port of nodes that don't declare their outputs.
+pub const DEFAULT_OUTPUT_PORT: &str = "out";
+
+/// The arguments and result of a plugin function.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct FunctionSignature {
+    pub parameters: Vec<FlowGraphPort>,
+    pub returns: ValueType,
+}
+
+impl FunctionSignature {
+    /// Builds a signature from the JSON schema of the function arguments, as
+    /// generated for plugin commands, and the optional schema of its result.
+    pub fn from_json_schema(
+        arguments: &serde_json::Value,
+        returns: Option<&serde_json::Value>,
+    ) -> Self {
+        let required = arguments
+            .get("required")
+            .and_then(|r| r.as_array())
+            .map(|r| r.iter().filter_map(|n| n.as_str()).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        let parameters = arguments
+            .get("properties")
+            .and_then(|p| p.as_object())
+            .map(|properties| {
+                properties
+                    .iter()
+                    .map(|(name, schema)| FlowGraphPort {
+                        id: name.clone(),
+                        value_type: ValueType::from_json_schema(schema),
+                        required: required.contains(&name.as_str()),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            parameters,
+            returns: returns.map(ValueType::from_json_schema).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found while checking a graph, attached to the node or edge the
+/// editor should underline.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub node_id: Option<String>,
+    pub edge_id: Option<String>,
+    pub kind: DiagnosticKind,
+    pub message: String,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DiagnosticKind {
+    TypeMismatch {
+        port: String,
+        expected: ValueType,
+        found: ValueType,
+    },
+    MissingArgument {
+        port: String,
+    },
+    UnknownFunction {
+        plugin: String,
+        function: String,
+    },
+    UnknownPort {
+        port: String,
+    },
+    Unreachable,
+    /// The graph can't be scheduled at all, e.g. because it contains a cycle.
+    Structure,
+}
+
+/// The resolved ports of a node: declared in its `data`, or derived from the
+/// plugin function or function graph schema it represents.
+#[derive(Clone, Debug, Default)]
+pub struct NodePorts {
+    pub inputs: Vec<FlowGraphPort>,
+    pub outputs: Vec<FlowGraphPort>,
+}
+
+impl NodePorts {
+    pub fn resolve(node: &FlowGraphNode, catalog: &dyn FunctionCatalog) -> Self {
+        let mut ports = Self {
+            inputs: node.input_ports(),
+            outputs: node.output_ports(),
+        };
+
+        match node.node_type.as_str() {
+            node_types::FOREIGN_FUNCTION_NODE => {
+                let signature = function_of(node)
+                    .and_then(|(plugin, function)| catalog.signature(plugin, function));
+
+                if let Some(signature) = signature {
+                    if ports.inputs.is_empty() {
+                        ports.inputs = signature.parameters;
+                    }
+                    if ports.outputs.is_empty() {
+                        ports.outputs = vec![port(DEFAULT_OUTPUT_PORT, signature.returns)];
+                    }
+                }
+            }
+            node_types::FN_ENTRY if ports.outputs.is_empty() => {
+                ports.outputs = schema_ports(node);
+            }
+            node_types::FN_TRIGGER if ports.inputs.is_empty() => {
+                if let Some(schema) = node
+                    .data
+                    .get("graphName")
+                    .and_then(|graph| graph.as_str())
+                    .and_then(|graph| catalog.graph_schema(graph))
+                {
+                    ports.inputs = schema;
+                }
+            }
+            _ => {}
+        }
+
+        ports
+    }
+
+    fn output_type(&self, handle: Option<&String>) -> Option<ValueType> {
+        find_port_type(&self.outputs, handle)
+    }
+
+    fn input_type(&self, handle: Option<&String>) -> Option<ValueType> {
+        find_port_type(&self.inputs, handle)
+    }
+}
+
+/// The arguments a function graph accepts, declared on its `fnEntry` node as
+/// `schema: { name: type }`. All of them are required.
+pub fn schema_ports(node: &FlowGraphNode) -> Vec<FlowGraphPort> {
+    node.data
+        .get("schema")
+        .and_then(|schema| schema.as_object())
+        .map(|schema| {
+            schema
+                .iter()
+                .map(|(name, value_type)| FlowGraphPort {
+                    id: name.clone(),
+                    value_type: serde_json::from_value(value_type.clone()).unwrap_or_default(),
+                    required: true,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The arguments of the function graph `graph`, from the schema of its
+/// `fnEntry` node. `None` when it isn't a function graph.
+pub fn graph_schema(graph: &FlowGraph) -> Option<Vec<FlowGraphPort>> {
+    graph
+        .nodes
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .find(|node| node.node_type == node_types::FN_ENTRY)
+        .map(schema_ports)
+}
+
+/// Checks `graph` against the function signatures and graph schemas in `catalog` and returns every problem found.
+pub fn check(graph: &FlowGraph, catalog: &dyn FunctionCatalog) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let nodes = graph.nodes.as_deref().unwrap_or_default();
+    let edges = graph.edges.as_deref().unwrap_or_default();
+
+    match schedule_flow_graph(graph) {
+        Ok((dependencies, schedule)) => {
+            for index in schedule.unreachable {
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Warning,
+                    node_id: Some(dependencies.node_ids[index].clone()),
+                    edge_id: None,
+                    kind: DiagnosticKind::Unreachable,
+                    message: "node can't be reached from an entry node and will never run"
+                        .to_string(),
+                });
+            }
+        }
+        Err(error) => diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            node_id: error.node_id.clone(),
+            edge_id: None,
+            kind: DiagnosticKind::Structure,
+            message: error.kind.to_string(),
+        }),
+    }
+
+    let ports = nodes
+        .iter()
+        .map(|node| (node.id.as_str(), NodePorts::resolve(node, catalog)))
+        .collect::<HashMap<_, _>>();
+
+    for node in nodes {
+        if node.node_type != node_types::FOREIGN_FUNCTION_NODE {
+            continue;
+        }
+
+        if let Some((plugin, function)) = function_of(node)
+            && !catalog.contains(plugin, function)
+        {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                node_id: Some(node.id.clone()),
+                edge_id: None,
+                kind: DiagnosticKind::UnknownFunction {
+                    plugin: plugin.to_string(),
+                    function: function.to_string(),
+                },
+                message: format!("no loaded plugin exposes `{plugin}::{function}`"),
+            });
+        }
+    }
+
+    for edge in edges {
+        let (Some(source), Some(target)) = (
+            ports.get(edge.source.as_str()),
+            ports.get(edge.target.as_str()),
+        ) else {
+            continue;
+        };
+
+        let edge_diagnostic =
+            |node_id: &String, kind: DiagnosticKind, message: String| Diagnostic {
+                severity: Severity::Error,
+                node_id: Some(node_id.clone()),
+                edge_id: Some(edge.id.clone()),
+                kind,
+                message,
+            };
+
+        let Some(found) = source.output_type(edge.source_handle.as_ref()) else {
+            let port = edge.source_handle.clone().unwrap_or_default();
+            diagnostics.push(edge_diagnostic(
+                &edge.source,
+                DiagnosticKind::UnknownPort { port: port.clone() },
+                format!("`{}` has no output `{port}`", edge.source),
+            ));
+            continue;
+        };
+
+        let Some(expected) = target.input_type(edge.target_handle.as_ref()) else {
+            let port = edge.target_handle.clone().unwrap_or_default();
+            diagnostics.push(edge_diagnostic(
+                &edge.target,
+                DiagnosticKind::UnknownPort { port: port.clone() },
+                format!("`{}` has no input `{port}`", edge.target),
+            ));
+            continue;
+        };
+
+        let port = edge.target_handle.clone().unwrap_or_default();
+        let kind = DiagnosticKind::TypeMismatch {
+            port: port.clone(),
+            expected,
+            found,
+        };
+
+        match found.coercion_to(expected) {
+            Coercion::Impossible => diagnostics.push(edge_diagnostic(
+                &edge.target,
+                kind,
+                format!("expected {expected} but the edge carries {found}"),
+            )),
+            Coercion::Fallible if found != ValueType::Any => diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                ..edge_diagnostic(
+                    &edge.target,
+                    kind,
+                    format!("{found} may not convert into {expected} at runtime"),
+                )
+            }),
+            _ => {}
+        }
+    }
+
+    for node in nodes {
+        let Some(node_ports) = ports.get(node.id.as_str()) else {
+            continue;
+        };
+
+        let incoming = edges
+            .iter()
+            .filter(|edge| edge.target == node.id)
+            .collect::<Vec<_>>();
+
+        // An edge without a handle may carry an object holding any of the arguments.
+        if incoming.iter().any(|edge| edge.target_handle.is_none()) {
+            continue;
+        }
+
+        for input in node_ports.inputs.iter().filter(|p| p.required) {
+            let connected = incoming
+                .iter()
+                .any(|edge| edge.target_handle.as_deref() == Some(input.id.as_str()));
+            let constant = node
+                .data
+                .get("arguments")
+                .and_then(|arguments| arguments.get(&input.id))
+                .is_some();
+
+            if !connected && !constant {
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Error,
+                    node_id: Some(node.id.clone()),
+                    edge_id: None,
+                    kind: DiagnosticKind::MissingArgument {
+                        port: input.id.clone(),
+                    },
+                    message: format!("required argument `{}` is not connected", input.id),
+                });
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Looks up the type of the port named `handle`.
+///
+/// Without a handle, the only declared port is used. Nodes without declared
+/// ports accept any handle and carry untyped values.
+fn find_port_type(ports: &[FlowGraphPort], handle: Option<&String>) -> Option<ValueType> {
+    match (handle, ports) {
+        (_, []) => Some(ValueType::Any),
+        (None, [single]) => Some(single.value_type),
+        (None, _) => Some(ValueType::Any),
+        (Some(handle), ports) => ports
+            .iter()
+            .find(|port| port.id == *handle)
+            .map(|port| port.value_type),
+    }
+}
+
+fn function_of(node: &FlowGraphNode) -> Option<(&str, &str)> {
+    let plugin = node.data.get("pluginName")?.as_str()?;
+    let function = node.data.get("functionName")?.as_str()?;
+    Some((plugin, function))
+}
+
+fn port(id: &str, value_type: ValueType) -> FlowGraphPort {
+    FlowGraphPort {
+        id: id.to_string(),
+        value_type,
+        required: false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use flow_rt_shared::{
+        schemas::{FlowGraphEdge, FlowGraphPort},
+        value::ValueType,
+    };
+    use serde_json::json;
+
+    use crate::{
+        compiler::FunctionCatalog,
+        testing::{edge, function_node, graph, node, port_edge},
+        typecheck::{DiagnosticKind, FunctionSignature, Severity, check},
+    };
+
+    struct Catalog;
+
+    impl FunctionCatalog for Catalog {
+        fn contains(&self, plugin: &str, function: &str) -> bool {
+            plugin == "http-module" && function == "fetch"
+        }
+
+        fn signature(&self, plugin: &str, function: &str) -> Option<FunctionSignature> {
+            self.contains(plugin, function).then(|| {
+                FunctionSignature::from_json_schema(
+                    &json!({
+                        "type": "object",
+                        "properties": {
+                            "url": { "type": "string" },
+                            "properties": { "$ref": "#/definitions/FetchRequest" }
+                        },
+                        "required": ["url", "properties"]
+                    }),
+                    Some(&json!({ "type": "object" })),
+                )
+            })
+        }
+
+        fn graph_schema(&self, graph: &str) -> Option<Vec<FlowGraphPort>> {
+            (graph == "scan").then(|| {
+                vec![
+                    FlowGraphPort {
+                        id: "host".into(),
+                        value_type: ValueType::String,
+                        required: true,
+                    },
+                    FlowGraphPort {
+                        id: "ports".into(),
+                        value_type: ValueType::Array,
+                        required: true,
+                    },
+                ]
+            })
+        }
+    }
+
+    #[test]
+    fn check_should_report_mismatched_and_missing_arguments() {
+        let graph = graph(
+            vec![
+                node(
+                    "entry",
+                    "fnEntry",
+                    json!({ "schema": { "target": "array" } }),
+                ),
+                function_node("fetch", "http-module", "fetch"),
+            ],
+            vec![FlowGraphEdge {
+                target_handle: Some("properties".into()),
+                ..port_edge("entry", "target", "fetch")
+            }],
+        );
+
+        let diagnostics = check(&graph, &Catalog);
+
+        assert!(
+            diagnostics
+                .iter()
+                .any(|d| d.edge_id.as_deref() == Some("entry->fetch")
+                    && d.kind
+                        == DiagnosticKind::TypeMismatch {
+                            port: "properties".into(),
+                            expected: ValueType::Object,
+                            found: ValueType::Array,
+                        })
+        );
+        assert!(
+            diagnostics
+                .iter()
+                .any(|d| d.node_id.as_deref() == Some("fetch")
+                    && d.kind == DiagnosticKind::MissingArgument { port: "url".into() })
+        );
+    }
+
+    #[test]
+    fn check_should_report_graph_calls_not_matching_their_schema() {
+        let graph = graph(
+            vec![
+                node(
+                    "entry",
+                    "fnEntry",
+                    json!({ "schema": { "target": "object" } }),
+                ),
+                node("call", "fnTrigger", json!({ "graphName": "scan" })),
+            ],
+            vec![FlowGraphEdge {
+                target_handle: Some("ports".into()),
+                ..port_edge("entry", "target", "call")
+            }],
+        );
+
+        let diagnostics = check(&graph, &Catalog);
+
+        assert!(
+            diagnostics
+                .iter()
+                .any(|d| d.edge_id.as_deref() == Some("entry->call")
+                    && d.kind
+                        == DiagnosticKind::TypeMismatch {
+                            port: "ports".into(),
+                            expected: ValueType::Array,
+                            found: ValueType::Object,
+                        })
+        );
+        assert!(
+            diagnostics
+                .iter()
+                .any(|d| d.node_id.as_deref() == Some("call")
+                    && d.kind
+                        == DiagnosticKind::MissingArgument {
+                            port: "host".into()
+                        })
+        );
+    }
+
+    #[test]
+    fn check_should_report_unknown_functions() {
+        let graph = graph(
+            vec![
+                node("start", "startNode", json!({})),
+                function_node("nmap", "test-nmap-module", "nmap_run"),
+            ],
+            vec![edge("start", "nmap")],
+        );
+
+        let diagnostics = check(&graph, &Catalog);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert!(matches!(
+            diagnostics[0].kind,
+            DiagnosticKind::UnknownFunction { .. }
+        ));
+    }
+}
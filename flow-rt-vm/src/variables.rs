@@ -0,0 +1,43 @@
+use std::sync::Mutex;
+
+use flow_rt_shared::value::Value;
+
+use crate::ir::{Variable, VariableId};
+
+/// The values of the variables of one run, or of one function graph call.
+///
+/// Nodes reading and writing a variable are ordered by their edges only, so
+/// nodes on parallel branches see each other's writes in whatever order they
+/// happen to run.
+#[derive(Debug, Default)]
+pub struct VariableScope {
+    values: Mutex<Vec<Value>>,
+}
+
+impl VariableScope {
+    /// A scope where every variable holds its default.
+    pub fn new(variables: &[Variable]) -> Self {
+        Self {
+            values: Mutex::new(variables.iter().map(|v| v.default.clone()).collect()),
+        }
+    }
+
+    pub fn get(&self, id: VariableId) -> Option<Value> {
+        self.lock().get(id).cloned()
+    }
+
+    /// Stores `value` in the variable `id`. Returns `false` when there is no such variable.
+    pub fn set(&self, id: VariableId, value: Value) -> bool {
+        match self.lock().get_mut(id) {
+            Some(slot) => {
+                *slot = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Value>> {
+        self.values.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -1,8 +1,9 @@
 use std::{str::FromStr, sync::OnceLock, time::Duration};
 
 use dyn_rt::{
-    expose, serde::{Deserialize, Serialize}, serde_json, utils::{Plugin, PluginBuilder}
+    expose, serde::{Deserialize, Serialize}, utils::{Plugin, PluginBuilder}
 };
+use flow_rt_shared::value::Value;
 use reqwest::{Client, Method};
 
 static HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
@@ -34,7 +35,7 @@ struct FetchRequest {
 }
 
 #[dyn_rt::macros::command]
-async fn fetch(url: String, properties: FetchRequest) -> Result<serde_json::Value, ()>
+async fn fetch(url: String, properties: FetchRequest) -> Result<Value, ()>
 {
     let method = Method::from_str(&properties.method);
     if method.is_err() {
@@ -66,7 +67,7 @@ async fn fetch(url: String, properties: FetchRequest) -> Result<serde_json::Valu
     }
 
     let res = res.unwrap();
-    let obj = res.json().await;
+    let obj = res.json::<Value>().await;
     if let Err(e) = obj {
         // Deserialize issue
         return Err(());